@@ -0,0 +1,105 @@
+use super::ray::*;
+use super::vec3::*;
+
+#[derive(Copy, Clone)]
+pub struct Aabb {
+    pub minimum: Point3,
+    pub maximum: Point3,
+}
+
+impl Default for Aabb {
+    fn default() -> Aabb {
+        // An empty box: surrounding it with any other box yields that box.
+        Aabb {
+            minimum: Point3 {
+                x: f64::INFINITY,
+                y: f64::INFINITY,
+                z: f64::INFINITY,
+            },
+            maximum: Point3 {
+                x: -f64::INFINITY,
+                y: -f64::INFINITY,
+                z: -f64::INFINITY,
+            },
+        }
+    }
+}
+
+impl Aabb {
+    pub fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> bool {
+        let mut t_min = t_min;
+        let mut t_max = t_max;
+        for axis in 0..3 {
+            let inv_d = 1.0 / r.direction[axis];
+            let mut t0 = (self.minimum[axis] - r.origin[axis]) * inv_d;
+            let mut t1 = (self.maximum[axis] - r.origin[axis]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            t_min = if t0 > t_min { t0 } else { t_min };
+            t_max = if t1 < t_max { t1 } else { t_max };
+            if t_max <= t_min {
+                return false;
+            }
+        }
+        return true;
+    }
+
+    // Grows degenerate dimensions so flat shapes still have a box that rays
+    // can hit.
+    pub fn pad(&self) -> Aabb {
+        let delta = 0.0001;
+        let mut minimum = self.minimum;
+        let mut maximum = self.maximum;
+        if maximum.x - minimum.x < delta {
+            minimum.x -= delta / 2.0;
+            maximum.x += delta / 2.0;
+        }
+        if maximum.y - minimum.y < delta {
+            minimum.y -= delta / 2.0;
+            maximum.y += delta / 2.0;
+        }
+        if maximum.z - minimum.z < delta {
+            minimum.z -= delta / 2.0;
+            maximum.z += delta / 2.0;
+        }
+        return Aabb { minimum, maximum };
+    }
+
+    pub fn centroid(&self) -> Point3 {
+        return (self.minimum + self.maximum) * 0.5;
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        let d = self.maximum - self.minimum;
+        if d.x < 0.0 || d.y < 0.0 || d.z < 0.0 {
+            return 0.0;
+        }
+        return 2.0 * (d.x * d.y + d.y * d.z + d.z * d.x);
+    }
+
+    pub fn longest_axis(&self) -> usize {
+        let d = self.maximum - self.minimum;
+        if d.x > d.y && d.x > d.z {
+            return 0;
+        }
+        if d.y > d.z {
+            return 1;
+        }
+        return 2;
+    }
+}
+
+pub fn surrounding_box(box0: Aabb, box1: Aabb) -> Aabb {
+    return Aabb {
+        minimum: min_vec3(box0.minimum, box1.minimum),
+        maximum: max_vec3(box0.maximum, box1.maximum),
+    };
+}
+
+pub fn surrounding_point(bbox: Aabb, p: Point3) -> Aabb {
+    return Aabb {
+        minimum: min_vec3(bbox.minimum, p),
+        maximum: max_vec3(bbox.maximum, p),
+    };
+}
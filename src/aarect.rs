@@ -0,0 +1,290 @@
+use super::aabb::*;
+use super::hit_record::*;
+use super::hittable::*;
+use super::material::*;
+use super::quad::*;
+use super::ray::*;
+use super::rng::*;
+use super::vec3::*;
+use std::sync::Arc;
+
+// Rectangles lying in a plane of constant z, y or x. The outward normal points
+// along the positive axis; u and v run from 0 to 1 across the two extents.
+// As lights they are sampled like quads, through the corner and edges that
+// `parallelogram` gives.
+
+pub struct XyRect {
+    pub x0: f64,
+    pub x1: f64,
+    pub y0: f64,
+    pub y1: f64,
+    pub k: f64,
+    pub material: Arc<dyn Material>,
+}
+
+pub struct XzRect {
+    pub x0: f64,
+    pub x1: f64,
+    pub z0: f64,
+    pub z1: f64,
+    pub k: f64,
+    pub material: Arc<dyn Material>,
+}
+
+pub struct YzRect {
+    pub y0: f64,
+    pub y1: f64,
+    pub z0: f64,
+    pub z1: f64,
+    pub k: f64,
+    pub material: Arc<dyn Material>,
+}
+
+impl XyRect {
+    fn parallelogram(&self) -> (Point3, Vec3, Vec3) {
+        let q = Point3 {
+            x: self.x0,
+            y: self.y0,
+            z: self.k,
+        };
+        let u = Vec3 {
+            x: self.x1 - self.x0,
+            y: 0.0,
+            z: 0.0,
+        };
+        let v = Vec3 {
+            x: 0.0,
+            y: self.y1 - self.y0,
+            z: 0.0,
+        };
+        return (q, u, v);
+    }
+}
+
+impl XzRect {
+    fn parallelogram(&self) -> (Point3, Vec3, Vec3) {
+        let q = Point3 {
+            x: self.x0,
+            y: self.k,
+            z: self.z0,
+        };
+        let u = Vec3 {
+            x: self.x1 - self.x0,
+            y: 0.0,
+            z: 0.0,
+        };
+        let v = Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: self.z1 - self.z0,
+        };
+        return (q, u, v);
+    }
+}
+
+impl YzRect {
+    fn parallelogram(&self) -> (Point3, Vec3, Vec3) {
+        let q = Point3 {
+            x: self.k,
+            y: self.y0,
+            z: self.z0,
+        };
+        let u = Vec3 {
+            x: 0.0,
+            y: self.y1 - self.y0,
+            z: 0.0,
+        };
+        let v = Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: self.z1 - self.z0,
+        };
+        return (q, u, v);
+    }
+}
+
+impl Hittable for XyRect {
+    fn hit(
+        &self,
+        _: &mut RandomNumberGenerator,
+        r: Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+    ) -> bool {
+        let t = (self.k - r.origin.z) / r.direction.z;
+        if t < t_min || t > t_max || t.is_nan() {
+            return false;
+        }
+        let x = r.origin.x + t * r.direction.x;
+        let y = r.origin.y + t * r.direction.y;
+        if x < self.x0 || x > self.x1 || y < self.y0 || y > self.y1 {
+            return false;
+        }
+
+        rec.u = (x - self.x0) / (self.x1 - self.x0);
+        rec.v = (y - self.y0) / (self.y1 - self.y0);
+        rec.t = t;
+        rec.p = r.at(t);
+        let outward_normal = Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: 1.0,
+        };
+        rec.set_face_normal(r, outward_normal);
+        rec.material = self.material.clone();
+
+        return true;
+    }
+
+    fn bounding_box(&self, output_box: &mut Aabb) -> bool {
+        *output_box = Aabb {
+            minimum: Point3 {
+                x: self.x0,
+                y: self.y0,
+                z: self.k,
+            },
+            maximum: Point3 {
+                x: self.x1,
+                y: self.y1,
+                z: self.k,
+            },
+        }
+        .pad();
+        return true;
+    }
+
+    fn pdf_value(&self, rng: &mut RandomNumberGenerator, origin: Point3, direction: Vec3) -> f64 {
+        let (_, u, v) = self.parallelogram();
+        return parallelogram_pdf_value(self, rng, u, v, origin, direction);
+    }
+
+    fn random(&self, rng: &mut RandomNumberGenerator, origin: Point3) -> Vec3 {
+        let (q, u, v) = self.parallelogram();
+        return random_on_parallelogram(rng, q, u, v, origin);
+    }
+}
+
+impl Hittable for XzRect {
+    fn hit(
+        &self,
+        _: &mut RandomNumberGenerator,
+        r: Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+    ) -> bool {
+        let t = (self.k - r.origin.y) / r.direction.y;
+        if t < t_min || t > t_max || t.is_nan() {
+            return false;
+        }
+        let x = r.origin.x + t * r.direction.x;
+        let z = r.origin.z + t * r.direction.z;
+        if x < self.x0 || x > self.x1 || z < self.z0 || z > self.z1 {
+            return false;
+        }
+
+        rec.u = (x - self.x0) / (self.x1 - self.x0);
+        rec.v = (z - self.z0) / (self.z1 - self.z0);
+        rec.t = t;
+        rec.p = r.at(t);
+        let outward_normal = Vec3 {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        };
+        rec.set_face_normal(r, outward_normal);
+        rec.material = self.material.clone();
+
+        return true;
+    }
+
+    fn bounding_box(&self, output_box: &mut Aabb) -> bool {
+        *output_box = Aabb {
+            minimum: Point3 {
+                x: self.x0,
+                y: self.k,
+                z: self.z0,
+            },
+            maximum: Point3 {
+                x: self.x1,
+                y: self.k,
+                z: self.z1,
+            },
+        }
+        .pad();
+        return true;
+    }
+
+    fn pdf_value(&self, rng: &mut RandomNumberGenerator, origin: Point3, direction: Vec3) -> f64 {
+        let (_, u, v) = self.parallelogram();
+        return parallelogram_pdf_value(self, rng, u, v, origin, direction);
+    }
+
+    fn random(&self, rng: &mut RandomNumberGenerator, origin: Point3) -> Vec3 {
+        let (q, u, v) = self.parallelogram();
+        return random_on_parallelogram(rng, q, u, v, origin);
+    }
+}
+
+impl Hittable for YzRect {
+    fn hit(
+        &self,
+        _: &mut RandomNumberGenerator,
+        r: Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+    ) -> bool {
+        let t = (self.k - r.origin.x) / r.direction.x;
+        if t < t_min || t > t_max || t.is_nan() {
+            return false;
+        }
+        let y = r.origin.y + t * r.direction.y;
+        let z = r.origin.z + t * r.direction.z;
+        if y < self.y0 || y > self.y1 || z < self.z0 || z > self.z1 {
+            return false;
+        }
+
+        rec.u = (y - self.y0) / (self.y1 - self.y0);
+        rec.v = (z - self.z0) / (self.z1 - self.z0);
+        rec.t = t;
+        rec.p = r.at(t);
+        let outward_normal = Vec3 {
+            x: 1.0,
+            y: 0.0,
+            z: 0.0,
+        };
+        rec.set_face_normal(r, outward_normal);
+        rec.material = self.material.clone();
+
+        return true;
+    }
+
+    fn bounding_box(&self, output_box: &mut Aabb) -> bool {
+        *output_box = Aabb {
+            minimum: Point3 {
+                x: self.k,
+                y: self.y0,
+                z: self.z0,
+            },
+            maximum: Point3 {
+                x: self.k,
+                y: self.y1,
+                z: self.z1,
+            },
+        }
+        .pad();
+        return true;
+    }
+
+    fn pdf_value(&self, rng: &mut RandomNumberGenerator, origin: Point3, direction: Vec3) -> f64 {
+        let (_, u, v) = self.parallelogram();
+        return parallelogram_pdf_value(self, rng, u, v, origin, direction);
+    }
+
+    fn random(&self, rng: &mut RandomNumberGenerator, origin: Point3) -> Vec3 {
+        let (q, u, v) = self.parallelogram();
+        return random_on_parallelogram(rng, q, u, v, origin);
+    }
+}
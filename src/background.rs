@@ -0,0 +1,71 @@
+use super::environment_map::*;
+use super::ray::*;
+use super::rng::*;
+use super::sky::*;
+use super::vec3::*;
+use std::sync::Arc;
+
+// Radiance of rays that leave the scene without hitting anything.
+#[derive(Clone)]
+pub enum Background {
+    // White at the horizon blending to light blue overhead.
+    Gradient,
+    Solid(Color),
+    Map(Arc<EnvironmentMap>),
+    Sky(Sky),
+}
+
+impl Background {
+    pub fn value(&self, r: Ray) -> Color {
+        return match self {
+            Background::Gradient => {
+                let unit_direction = unit_vector(r.direction);
+                let t = 0.5 * (unit_direction.y + 1.0);
+                let white = Color {
+                    x: 1.0,
+                    y: 1.0,
+                    z: 1.0,
+                };
+                let blue = Color {
+                    x: 0.5,
+                    y: 0.7,
+                    z: 1.0,
+                };
+                white * (1.0 - t) + blue * t
+            }
+            Background::Solid(color) => *color,
+            Background::Map(map) => map.value(r.direction),
+            Background::Sky(sky) => sky.value(r.direction),
+        };
+    }
+
+    // Only environment maps and the sky with its sun are sampled directly,
+    // the other backgrounds are smooth enough for the scattered rays to find.
+    pub fn is_sampled(&self) -> bool {
+        return match self {
+            Background::Map(map) => map.is_sampled(),
+            Background::Sky(_) => true,
+            _ => false,
+        };
+    }
+
+    pub fn pdf_value(&self, direction: Vec3) -> f64 {
+        return match self {
+            Background::Map(map) => map.pdf_value(direction),
+            Background::Sky(sky) => sky.pdf_value(direction),
+            _ => 0.0,
+        };
+    }
+
+    pub fn random(&self, rng: &mut RandomNumberGenerator) -> Vec3 {
+        return match self {
+            Background::Map(map) => map.random(rng),
+            Background::Sky(sky) => sky.random(rng),
+            _ => Vec3 {
+                x: 0.0,
+                y: 1.0,
+                z: 0.0,
+            },
+        };
+    }
+}
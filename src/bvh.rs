@@ -0,0 +1,223 @@
+use super::aabb::*;
+use super::hit_record::*;
+use super::hittable::*;
+use super::hittable_list::*;
+use super::ray::*;
+use super::rng::*;
+use super::vec3::*;
+use std::sync::Arc;
+
+const SAH_BIN_COUNT: usize = 16;
+
+pub struct BvhNode {
+    pub left: Arc<dyn Hittable>,
+    pub right: Arc<dyn Hittable>,
+    pub bbox: Aabb,
+}
+
+struct BuildItem {
+    object: Arc<dyn Hittable>,
+    bbox: Aabb,
+    centroid: Point3,
+}
+
+impl Hittable for BvhNode {
+    fn hit(
+        &self,
+        rng: &mut RandomNumberGenerator,
+        r: Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+    ) -> bool {
+        if !self.bbox.hit(r, t_min, t_max) {
+            return false;
+        }
+
+        let hit_left = self.left.hit(rng, r, t_min, t_max, rec);
+        let closest_so_far = if hit_left { rec.t } else { t_max };
+        let hit_right = self.right.hit(rng, r, t_min, closest_so_far, rec);
+
+        return hit_left || hit_right;
+    }
+
+    fn bounding_box(&self, output_box: &mut Aabb) -> bool {
+        *output_box = self.bbox;
+        return true;
+    }
+}
+
+// Builds a bounding volume hierarchy over the objects of the list, splitting
+// each node where the surface area heuristic estimates the cheapest traversal.
+// Objects without a bounding box cannot be placed in the tree and are tested
+// linearly next to it.
+pub fn build_bvh(list: HittableList) -> Arc<dyn Hittable> {
+    let mut items: Vec<BuildItem> = vec![];
+    let mut unbounded = HittableList { objects: vec![] };
+
+    for object in list.objects {
+        let mut bbox = Aabb::default();
+        if object.bounding_box(&mut bbox) {
+            items.push(BuildItem {
+                object,
+                bbox,
+                centroid: bbox.centroid(),
+            });
+        } else {
+            unbounded.add(object);
+        }
+    }
+
+    if items.is_empty() {
+        return Arc::new(unbounded);
+    }
+
+    let root = build_node(items.as_mut_slice());
+    if unbounded.objects.is_empty() {
+        return root;
+    }
+
+    unbounded.add(root);
+    return Arc::new(unbounded);
+}
+
+fn build_node(items: &mut [BuildItem]) -> Arc<dyn Hittable> {
+    if items.len() == 1 {
+        return items[0].object.clone();
+    }
+
+    let mut bbox = Aabb::default();
+    for item in items.iter() {
+        bbox = surrounding_box(bbox, item.bbox);
+    }
+
+    let mid = partition_sah(items);
+    let (left_items, right_items) = items.split_at_mut(mid);
+
+    return Arc::new(BvhNode {
+        left: build_node(left_items),
+        right: build_node(right_items),
+        bbox,
+    });
+}
+
+// Reorders the items and returns the number that go into the left child.
+fn partition_sah(items: &mut [BuildItem]) -> usize {
+    let mut centroid_bounds = Aabb::default();
+    for item in items.iter() {
+        centroid_bounds = surrounding_point(centroid_bounds, item.centroid);
+    }
+
+    let mut best_cost = f64::INFINITY;
+    let mut best_axis = centroid_bounds.longest_axis();
+    let mut best_left_count = items.len() / 2;
+
+    for axis in 0..3 {
+        let min = centroid_bounds.minimum[axis];
+        let extent = centroid_bounds.maximum[axis] - min;
+        if extent <= 0.0 {
+            continue;
+        }
+
+        let mut counts = [0usize; SAH_BIN_COUNT];
+        let mut boxes = [Aabb::default(); SAH_BIN_COUNT];
+        for item in items.iter() {
+            let bin = bin_index(item.centroid[axis], min, extent);
+            counts[bin] += 1;
+            boxes[bin] = surrounding_box(boxes[bin], item.bbox);
+        }
+
+        // Sweep from the right so each split can be costed in a single pass
+        // from the left.
+        let mut right_areas = [0.0; SAH_BIN_COUNT];
+        let mut right_box = Aabb::default();
+        for bin in (1..SAH_BIN_COUNT).rev() {
+            right_box = surrounding_box(right_box, boxes[bin]);
+            right_areas[bin] = right_box.surface_area();
+        }
+
+        let mut left_box = Aabb::default();
+        let mut left_count = 0;
+        for bin in 0..(SAH_BIN_COUNT - 1) {
+            left_box = surrounding_box(left_box, boxes[bin]);
+            left_count += counts[bin];
+            let right_count = items.len() - left_count;
+            if left_count == 0 || right_count == 0 {
+                continue;
+            }
+
+            let cost = left_box.surface_area() * left_count as f64
+                + right_areas[bin + 1] * right_count as f64;
+            if cost < best_cost {
+                best_cost = cost;
+                best_axis = axis;
+                best_left_count = left_count;
+            }
+        }
+    }
+
+    // Bins are ordered along the axis, so sorting by centroid puts exactly the
+    // items of the chosen left bins first. When every centroid coincides this
+    // falls back to an object median split.
+    items.sort_by(|a, b| a.centroid[best_axis].total_cmp(&b.centroid[best_axis]));
+    return best_left_count;
+}
+
+fn bin_index(value: f64, min: f64, extent: f64) -> usize {
+    let bin = ((value - min) / extent * SAH_BIN_COUNT as f64) as usize;
+    return bin.min(SAH_BIN_COUNT - 1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::diffuse_light::*;
+    use super::super::material::*;
+    use super::super::sphere::*;
+    use super::*;
+
+    // The material an object was made with tells which object was hit.
+    fn material_address(rec: &HitRecord) -> *const u8 {
+        return Arc::as_ptr(&rec.material) as *const u8;
+    }
+
+    // Random rays through a cloud of overlapping spheres of many sizes find
+    // the same nearest hit through the hierarchy as through the plain list.
+    #[test]
+    fn the_hierarchy_finds_the_same_hits_as_the_list() {
+        let mut rng = RandomNumberGenerator::create(5772, RngAlgorithm::Pcg32);
+        let mut list = HittableList { objects: vec![] };
+        for _ in 0..300 {
+            let material: Arc<dyn Material> = Arc::new(DiffuseLight {
+                emit: Color::default(),
+            });
+            list.add(Arc::new(Sphere {
+                center: rng.random_vec3_range(-10.0, 10.0),
+                radius: rng.random_range(0.05, 2.0),
+                material,
+            }));
+        }
+        let bvh = build_bvh(HittableList {
+            objects: list.objects.clone(),
+        });
+
+        let mut hits = 0;
+        for _ in 0..5000 {
+            let r = Ray {
+                origin: rng.random_vec3_range(-15.0, 15.0),
+                direction: rng.random_unit_vector(),
+                time: 0.0,
+            };
+            let mut list_rec = HitRecord::new();
+            let mut bvh_rec = HitRecord::new();
+            let list_hit = list.hit(&mut rng, r, 0.001, f64::INFINITY, &mut list_rec);
+            let bvh_hit = bvh.hit(&mut rng, r, 0.001, f64::INFINITY, &mut bvh_rec);
+            assert_eq!(list_hit, bvh_hit);
+            if list_hit {
+                hits += 1;
+                assert_eq!(list_rec.t, bvh_rec.t);
+                assert_eq!(material_address(&list_rec), material_address(&bvh_rec));
+            }
+        }
+        assert!(hits > 1000);
+    }
+}
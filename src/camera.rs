@@ -12,9 +12,12 @@ pub struct Camera {
     u: Vec3,
     v: Vec3,
     lens_radius: f64,
+    time0: f64, // shutter open/close times
+    time1: f64,
 }
 
 impl Camera {
+    #[allow(clippy::too_many_arguments)]
     pub fn create(
         lookfrom: Point3,
         lookat: Point3,
@@ -23,6 +26,8 @@ impl Camera {
         aspect_ratio: f64,
         aperture: f64,
         focus_dist: f64,
+        time0: f64,
+        time1: f64,
     ) -> Camera {
         let theta: f64 = degrees_to_radians(vfov);
         let h: f64 = (theta / 2.0).tan();
@@ -48,17 +53,27 @@ impl Camera {
             u,
             v,
             lens_radius,
+            time0,
+            time1,
         };
     }
 
     pub fn get_ray(self, rng: &mut RandomNumberGenerator, s: f64, t: f64) -> Ray {
         let rd = rng.random_in_unit_disk() * self.lens_radius;
         let offset = self.u * rd.x + self.v * rd.y;
+        // Only draw a time when the shutter is open for a while, so renders
+        // without motion blur use the same random numbers as before.
+        let time = if self.time1 > self.time0 {
+            rng.random_range(self.time0, self.time1)
+        } else {
+            self.time0
+        };
         return Ray {
             origin: self.origin + offset,
             direction: self.lower_left_corner + self.horizontal * s + self.vertical * t
                 - self.origin
                 - offset,
+            time,
         };
     }
 }
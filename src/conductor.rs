@@ -0,0 +1,229 @@
+use super::hit_record::*;
+use super::material::*;
+use super::microfacet::*;
+use super::ray::*;
+use super::rng::*;
+use super::vec3::*;
+
+// A metal described by its complex index of refraction eta + i k for a red,
+// a green and a blue wavelength, with a GGX microfacet surface. Unlike
+// `Metal`, it reflects the light the metal would: the colour comes from the
+// Fresnel equations, shifting towards white at grazing angles, and rough
+// surfaces lose no energy beyond what masking and shadowing hide.
+pub struct Conductor {
+    pub eta: Color,
+    pub k: Color,
+    pub distribution: Ggx,
+}
+
+impl Conductor {
+    pub fn create(eta: Color, k: Color, roughness: f64) -> Conductor {
+        return Conductor {
+            eta,
+            k,
+            distribution: Ggx::from_roughness(roughness),
+        };
+    }
+
+    // eta and k at 650, 550 and 450 nm of a few common metals.
+    pub fn preset(name: &str) -> Option<(Color, Color)> {
+        let (eta, k) = match name {
+            "gold" => ([0.143, 0.374, 1.442], [3.983, 2.385, 1.603]),
+            "copper" => ([0.200, 0.924, 1.102], [3.912, 2.452, 2.142]),
+            "aluminium" => ([1.657, 0.880, 0.521], [9.224, 6.270, 4.837]),
+            "silver" => ([0.155, 0.117, 0.138], [4.828, 3.122, 2.147]),
+            _ => return None,
+        };
+        let color = |c: [f64; 3]| Color {
+            x: c[0],
+            y: c[1],
+            z: c[2],
+        };
+        return Some((color(eta), color(k)));
+    }
+
+    fn fresnel(&self, cosine: f64) -> Color {
+        return Color {
+            x: fresnel_conductor(cosine, self.eta.x, self.k.x),
+            y: fresnel_conductor(cosine, self.eta.y, self.k.y),
+            z: fresnel_conductor(cosine, self.eta.z, self.k.z),
+        };
+    }
+}
+
+// The exact reflectance of unpolarized light arriving at the angle whose
+// cosine is `cosine` on a conductor with index eta + i k.
+fn fresnel_conductor(cosine: f64, eta: f64, k: f64) -> f64 {
+    let cos_squared = cosine * cosine;
+    let sin_squared = 1.0 - cos_squared;
+    let t0 = eta * eta - k * k - sin_squared;
+    let a_squared_plus_b_squared = (t0 * t0 + 4.0 * eta * eta * k * k).sqrt();
+    let a = (0.5 * (a_squared_plus_b_squared + t0)).max(0.0).sqrt();
+
+    let t1 = a_squared_plus_b_squared + cos_squared;
+    let t2 = 2.0 * cosine * a;
+    let rs = (t1 - t2) / (t1 + t2);
+
+    let t3 = cos_squared * a_squared_plus_b_squared + sin_squared * sin_squared;
+    let t4 = t2 * sin_squared;
+    let rp = rs * (t3 - t4) / (t3 + t4);
+    return (rs + rp) / 2.0;
+}
+
+impl Material for Conductor {
+    // Rays the surface does not reflect are absorbed.
+    fn diffuse(&self, _: &HitRecord) -> Color {
+        return Color {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        };
+    }
+
+    // Reflects about a microfacet normal picked among the ones seen from
+    // the incoming ray, weighted by the Fresnel term and the microfacets the
+    // reflected ray sees.
+    fn scatter(
+        &self,
+        rng: &mut RandomNumberGenerator,
+        r_in: Ray,
+        rec: &HitRecord,
+        attenuation: &mut Color,
+        scattered: &mut Ray,
+    ) -> bool {
+        let unit_direction = unit_vector(r_in.direction);
+        let cos_o = -dot(unit_direction, rec.normal);
+        if cos_o <= 0.0 {
+            return false;
+        }
+
+        if self.distribution.is_smooth() {
+            *scattered = Ray {
+                origin: rec.p,
+                direction: reflect(unit_direction, rec.normal),
+                time: r_in.time,
+            };
+            *attenuation = self.fresnel(cos_o);
+            return true;
+        }
+
+        let h = self
+            .distribution
+            .sample_visible_normal(rng, rec.normal, -unit_direction);
+        let direction = reflect(unit_direction, h);
+        let cos_i = dot(direction, rec.normal);
+        if cos_i <= 0.0 {
+            return false;
+        }
+
+        *scattered = Ray {
+            origin: rec.p,
+            direction,
+            time: r_in.time,
+        };
+        let masking = self.distribution.g2(cos_o, cos_i) / self.distribution.g1(cos_o);
+        *attenuation = self.fresnel(-dot(unit_direction, h)) * masking;
+        return true;
+    }
+
+    fn is_specular(&self) -> bool {
+        return self.distribution.is_smooth();
+    }
+
+    fn eval(&self, r_in: Ray, rec: &HitRecord, direction: Vec3) -> Color {
+        let black = Color {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        };
+        if self.distribution.is_smooth() {
+            return black;
+        }
+        let wo = -unit_vector(r_in.direction);
+        let wi = unit_vector(direction);
+        let cos_o = dot(wo, rec.normal);
+        let cos_i = dot(wi, rec.normal);
+        if cos_o <= 0.0 || cos_i <= 0.0 {
+            return black;
+        }
+        let h = unit_vector(wo + wi);
+        let d = self.distribution.d(dot(h, rec.normal));
+        let g = self.distribution.g2(cos_o, cos_i);
+        return self.fresnel(dot(wo, h)) * (d * g / (4.0 * cos_o));
+    }
+
+    // The density of the visible normal about which `direction` is the
+    // reflection, over the 4 (wo . h) by which reflection stretches it.
+    fn pdf(&self, r_in: Ray, rec: &HitRecord, direction: Vec3) -> f64 {
+        if self.distribution.is_smooth() {
+            return 0.0;
+        }
+        let wo = -unit_vector(r_in.direction);
+        let wi = unit_vector(direction);
+        let cos_o = dot(wo, rec.normal);
+        if cos_o <= 0.0 || dot(wi, rec.normal) <= 0.0 {
+            return 0.0;
+        }
+        let h = unit_vector(wo + wi);
+        let o_dot_h = dot(wo, h);
+        return self
+            .distribution
+            .visible_normal_pdf(cos_o, dot(h, rec.normal), o_dot_h)
+            / (4.0 * o_dot_h);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A direction picked by `scatter` is weighted by what `eval` over `pdf`
+    // gives for it, so light sampling and the scattered rays agree.
+    #[test]
+    fn the_scattered_weight_is_eval_over_pdf() {
+        let (eta, k) = Conductor::preset("gold").unwrap();
+        let mut rec = HitRecord::new();
+        rec.normal = Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: 1.0,
+        };
+        rec.front_face = true;
+        let mut rng = RandomNumberGenerator::create(1618, RngAlgorithm::Pcg32);
+        for roughness in [0.2, 0.5, 0.9] {
+            let conductor = Conductor::create(eta, k, roughness);
+            for cos_o in [0.95, 0.6, 0.15] {
+                let r_in = Ray {
+                    origin: Point3 {
+                        x: 0.0,
+                        y: 0.0,
+                        z: 0.0,
+                    },
+                    direction: Vec3 {
+                        x: f64::sqrt(1.0 - cos_o * cos_o),
+                        y: 0.0,
+                        z: -cos_o,
+                    },
+                    time: 0.0,
+                };
+                for _ in 0..100 {
+                    let mut attenuation = Color::default();
+                    let mut scattered = Ray::default();
+                    if !conductor.scatter(&mut rng, r_in, &rec, &mut attenuation, &mut scattered) {
+                        continue;
+                    }
+                    let pdf = conductor.pdf(r_in, &rec, scattered.direction);
+                    assert!(pdf > 0.0);
+                    let weight = conductor.eval(r_in, &rec, scattered.direction) / pdf;
+                    for (a, b) in [
+                        (weight.x, attenuation.x),
+                        (weight.y, attenuation.y),
+                        (weight.z, attenuation.z),
+                    ] {
+                        assert!((a - b).abs() < 1e-9 * b.max(1.0), "{} != {}", a, b);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use super::aabb::*;
+use super::hit_record::*;
+use super::hittable::*;
+use super::material::*;
+use super::ray::*;
+use super::rng::*;
+use super::vec3::*;
+use std::sync::Arc;
+
+// Fog or smoke of uniform density filling a convex boundary. A ray going
+// through it scatters after an exponentially distributed distance, or leaves
+// on the other side.
+pub struct ConstantMedium {
+    pub boundary: Arc<dyn Hittable>,
+    neg_inv_density: f64,
+    phase_function: Arc<dyn Material>,
+}
+
+impl ConstantMedium {
+    // The phase function is normally an Isotropic material.
+    pub fn create(
+        boundary: Arc<dyn Hittable>,
+        density: f64,
+        phase_function: Arc<dyn Material>,
+    ) -> ConstantMedium {
+        return ConstantMedium {
+            boundary,
+            neg_inv_density: -1.0 / density,
+            phase_function,
+        };
+    }
+}
+
+impl Hittable for ConstantMedium {
+    fn hit(
+        &self,
+        rng: &mut RandomNumberGenerator,
+        r: Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+    ) -> bool {
+        // Find where the ray enters and leaves the boundary along its whole
+        // line, so a ray starting inside still finds the far side.
+        let mut rec1 = HitRecord::new();
+        let mut rec2 = HitRecord::new();
+        if !self
+            .boundary
+            .hit(rng, r, -f64::INFINITY, f64::INFINITY, &mut rec1)
+        {
+            return false;
+        }
+        if !self
+            .boundary
+            .hit(rng, r, rec1.t + 0.0001, f64::INFINITY, &mut rec2)
+        {
+            return false;
+        }
+
+        let t_enter = rec1.t.max(t_min);
+        let t_exit = rec2.t.min(t_max);
+        if t_enter >= t_exit {
+            return false;
+        }
+
+        let ray_length = r.direction.length();
+        let distance_inside_boundary = (t_exit - t_enter) * ray_length;
+        let hit_distance = self.neg_inv_density * rng.random_double().ln();
+        if hit_distance > distance_inside_boundary {
+            return false;
+        }
+
+        rec.t = t_enter + hit_distance / ray_length;
+        rec.p = r.at(rec.t);
+        // The normal and side are arbitrary, the phase function ignores them.
+        rec.normal = Vec3 {
+            x: 1.0,
+            y: 0.0,
+            z: 0.0,
+        };
+        rec.front_face = true;
+        rec.u = 0.0;
+        rec.v = 0.0;
+        rec.material = self.phase_function.clone();
+
+        return true;
+    }
+
+    fn bounding_box(&self, output_box: &mut Aabb) -> bool {
+        return self.boundary.bounding_box(output_box);
+    }
+}
@@ -0,0 +1,104 @@
+use super::aabb::*;
+use super::hit_record::*;
+use super::hittable::*;
+use super::hittable_list::*;
+use super::material::*;
+use super::quad::*;
+use super::ray::*;
+use super::rng::*;
+use super::vec3::*;
+use std::sync::Arc;
+
+// An axis-aligned box made of six quads facing outwards.
+pub struct Cuboid {
+    pub minimum: Point3,
+    pub maximum: Point3,
+    sides: HittableList,
+}
+
+impl Cuboid {
+    pub fn create(a: Point3, b: Point3, material: Arc<dyn Material>) -> Cuboid {
+        let minimum = min_vec3(a, b);
+        let maximum = max_vec3(a, b);
+
+        let dx = Vec3 {
+            x: maximum.x - minimum.x,
+            y: 0.0,
+            z: 0.0,
+        };
+        let dy = Vec3 {
+            x: 0.0,
+            y: maximum.y - minimum.y,
+            z: 0.0,
+        };
+        let dz = Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: maximum.z - minimum.z,
+        };
+
+        let mut sides = HittableList { objects: vec![] };
+        let front = Point3 {
+            x: minimum.x,
+            y: minimum.y,
+            z: maximum.z,
+        };
+        let right = Point3 {
+            x: maximum.x,
+            y: minimum.y,
+            z: maximum.z,
+        };
+        let back = Point3 {
+            x: maximum.x,
+            y: minimum.y,
+            z: minimum.z,
+        };
+        let top = Point3 {
+            x: minimum.x,
+            y: maximum.y,
+            z: maximum.z,
+        };
+        sides.add(Arc::new(Quad::create(front, dx, dy, material.clone())));
+        sides.add(Arc::new(Quad::create(right, -dz, dy, material.clone())));
+        sides.add(Arc::new(Quad::create(back, -dx, dy, material.clone())));
+        sides.add(Arc::new(Quad::create(minimum, dz, dy, material.clone())));
+        sides.add(Arc::new(Quad::create(top, dx, -dz, material.clone())));
+        sides.add(Arc::new(Quad::create(minimum, dx, dz, material)));
+
+        return Cuboid {
+            minimum,
+            maximum,
+            sides,
+        };
+    }
+}
+
+impl Hittable for Cuboid {
+    fn hit(
+        &self,
+        rng: &mut RandomNumberGenerator,
+        r: Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+    ) -> bool {
+        return self.sides.hit(rng, r, t_min, t_max, rec);
+    }
+
+    fn bounding_box(&self, output_box: &mut Aabb) -> bool {
+        *output_box = Aabb {
+            minimum: self.minimum,
+            maximum: self.maximum,
+        }
+        .pad();
+        return true;
+    }
+
+    fn pdf_value(&self, rng: &mut RandomNumberGenerator, origin: Point3, direction: Vec3) -> f64 {
+        return self.sides.pdf_value(rng, origin, direction);
+    }
+
+    fn random(&self, rng: &mut RandomNumberGenerator, origin: Point3) -> Vec3 {
+        return self.sides.random(rng, origin);
+    }
+}
@@ -0,0 +1,112 @@
+use super::utils::*;
+use super::vec3::*;
+
+// The light a delta light sends to a point: the unit direction towards the
+// light, the distance to it, and the irradiance on a surface facing it.
+pub struct LightSample {
+    pub direction: Vec3,
+    pub distance: f64,
+    pub irradiance: Color,
+}
+
+// A light with no size, which scattered rays can never hit. It only
+// contributes when the integrator samples it with a shadow ray.
+pub trait DeltaLight: Send + Sync {
+    fn illuminate(&self, p: Point3) -> Option<LightSample>;
+}
+
+// Shines equally in all directions, falling off with the inverse square of
+// the distance.
+pub struct PointLight {
+    pub position: Point3,
+    pub intensity: Color,
+}
+
+impl DeltaLight for PointLight {
+    fn illuminate(&self, p: Point3) -> Option<LightSample> {
+        let to_light = self.position - p;
+        let distance_squared = to_light.length_squared();
+        if distance_squared <= 0.0 {
+            return None;
+        }
+        let distance = distance_squared.sqrt();
+        return Some(LightSample {
+            direction: to_light / distance,
+            distance,
+            irradiance: self.intensity / distance_squared,
+        });
+    }
+}
+
+// A point light limited to a cone around `direction`. The light fades out
+// over the last `edge` degrees towards the cone's half-angle `angle`.
+pub struct SpotLight {
+    position: Point3,
+    direction: Vec3,
+    intensity: Color,
+    cos_outer: f64,
+    cos_inner: f64,
+}
+
+impl SpotLight {
+    pub fn create(
+        position: Point3,
+        direction: Vec3,
+        intensity: Color,
+        angle: f64,
+        edge: f64,
+    ) -> SpotLight {
+        return SpotLight {
+            position,
+            direction: unit_vector(direction),
+            intensity,
+            cos_outer: degrees_to_radians(angle).cos(),
+            cos_inner: degrees_to_radians((angle - edge).max(0.0)).cos(),
+        };
+    }
+}
+
+impl DeltaLight for SpotLight {
+    fn illuminate(&self, p: Point3) -> Option<LightSample> {
+        let to_light = self.position - p;
+        let distance_squared = to_light.length_squared();
+        if distance_squared <= 0.0 {
+            return None;
+        }
+        let distance = distance_squared.sqrt();
+        let direction = to_light / distance;
+
+        let cos_theta = -dot(direction, self.direction);
+        if cos_theta <= self.cos_outer {
+            return None;
+        }
+        // Smoothstep across the soft edge of the cone.
+        let mut falloff = 1.0;
+        if cos_theta < self.cos_inner {
+            let t = (cos_theta - self.cos_outer) / (self.cos_inner - self.cos_outer);
+            falloff = t * t * (3.0 - 2.0 * t);
+        }
+        return Some(LightSample {
+            direction,
+            distance,
+            irradiance: self.intensity * (falloff / distance_squared),
+        });
+    }
+}
+
+// Parallel light shining along `direction` from infinitely far away, with
+// the same irradiance everywhere.
+pub struct DirectionalLight {
+    pub direction: Vec3,
+    pub irradiance: Color,
+}
+
+impl DeltaLight for DirectionalLight {
+    fn illuminate(&self, _p: Point3) -> Option<LightSample> {
+        return Some(LightSample {
+            direction: -unit_vector(self.direction),
+            distance: f64::INFINITY,
+            irradiance: self.irradiance,
+        });
+    }
+}
@@ -4,9 +4,46 @@ use super::ray::*;
 use super::rng::*;
 use super::vec3::*;
 
+// Glass with index of refraction `ir`. Light travelling inside it is
+// absorbed at the rate `absorption` per unit of distance, so that thick glass
+// and liquids are tinted more than thin ones.
 #[derive(Copy, Clone)]
 pub struct Dielectric {
     pub ir: f64,
+    pub absorption: Color,
+}
+
+impl Dielectric {
+    // Glass that absorbs nothing, as in the book.
+    pub fn clear(ir: f64) -> Dielectric {
+        return Dielectric {
+            ir,
+            absorption: Color {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+            },
+        };
+    }
+}
+
+// The fraction of the light that gets through a medium with `absorption`
+// along `r_in`, by the Beer-Lambert law. The ray only travelled inside when
+// it hits the surface from the back.
+pub fn transmittance(absorption: Color, r_in: Ray, rec: &HitRecord) -> Color {
+    if rec.front_face {
+        return Color {
+            x: 1.0,
+            y: 1.0,
+            z: 1.0,
+        };
+    }
+    let distance = (rec.p - r_in.origin).length();
+    return Color {
+        x: (-absorption.x * distance).exp(),
+        y: (-absorption.y * distance).exp(),
+        z: (-absorption.z * distance).exp(),
+    };
 }
 
 fn reflectance(cosine: f64, ref_idx: f64) -> f64 {
@@ -17,7 +54,7 @@ fn reflectance(cosine: f64, ref_idx: f64) -> f64 {
 }
 
 impl Material for Dielectric {
-    fn diffuse(&self) -> Color {
+    fn diffuse(&self, _: &HitRecord) -> Color {
         return Vec3 {
             x: 1.0,
             y: 1.0,
@@ -33,11 +70,7 @@ impl Material for Dielectric {
         attenuation: &mut Color,
         scattered: &mut Ray,
     ) -> bool {
-        *attenuation = Color {
-            x: 1.0,
-            y: 1.0,
-            z: 1.0,
-        };
+        *attenuation = transmittance(self.absorption, r_in, rec);
 
         let refraction_ratio = if rec.front_face {
             1.0 / self.ir
@@ -77,6 +110,7 @@ impl Material for Dielectric {
         *scattered = Ray {
             origin: rec.p,
             direction,
+            time: r_in.time,
         };
         return true;
     }
@@ -0,0 +1,39 @@
+use super::hit_record::*;
+use super::material::*;
+use super::ray::*;
+use super::rng::*;
+use super::vec3::*;
+
+#[derive(Copy, Clone)]
+pub struct DiffuseLight {
+    pub emit: Color,
+}
+
+impl Material for DiffuseLight {
+    fn diffuse(&self, _: &HitRecord) -> Color {
+        return Color {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        };
+    }
+
+    fn scatter(
+        &self,
+        _: &mut RandomNumberGenerator,
+        _: Ray,
+        _: &HitRecord,
+        _: &mut Color,
+        _: &mut Ray,
+    ) -> bool {
+        return false;
+    }
+
+    fn emitted(&self, _: Ray, _: &HitRecord) -> Color {
+        return self.emit;
+    }
+
+    fn is_emissive(&self) -> bool {
+        return true;
+    }
+}
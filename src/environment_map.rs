@@ -0,0 +1,134 @@
+use super::image_reader::*;
+use super::rng::*;
+use super::vec3::*;
+use std::f64::consts::PI;
+
+// An equirectangular (latitude-longitude) image of the light arriving from
+// every direction. The centre of the image lies towards -z, the top row is
+// straight up, and the map is turned by `rotation` degrees about the y axis.
+pub struct EnvironmentMap {
+    image: ImageData,
+    intensity: f64,
+    // From the scene to the map and back.
+    to_map: Mat4,
+    from_map: Mat4,
+    // Running sums of the sampling weights along each row, and of the row
+    // totals down the image.
+    column_cdf: Vec<f64>,
+    row_cdf: Vec<f64>,
+}
+
+fn luminance(c: Color) -> f64 {
+    return 0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z;
+}
+
+impl EnvironmentMap {
+    pub fn load(path: &str, rotation: f64, intensity: f64) -> Result<EnvironmentMap, String> {
+        let image = read_image(path)?;
+
+        // Pixels are picked in proportion to their luminance times the solid
+        // angle they cover, which shrinks towards the poles.
+        let mut column_cdf: Vec<f64> = Vec::with_capacity(image.width * image.height);
+        let mut row_cdf: Vec<f64> = Vec::with_capacity(image.height);
+        let mut total = 0.0;
+        for y in 0..image.height {
+            let sin_theta = (PI * (y as f64 + 0.5) / image.height as f64).sin();
+            let mut row_total = 0.0;
+            for x in 0..image.width {
+                row_total += luminance(image.pixel(x, y)).max(0.0) * sin_theta;
+                column_cdf.push(row_total);
+            }
+            total += row_total;
+            row_cdf.push(total);
+        }
+
+        let from_map = Mat4::rotation(
+            Vec3 {
+                x: 0.0,
+                y: 1.0,
+                z: 0.0,
+            },
+            rotation,
+        );
+        return Ok(EnvironmentMap {
+            image,
+            intensity,
+            to_map: from_map.transpose(),
+            from_map,
+            column_cdf,
+            row_cdf,
+        });
+    }
+
+    // The pixel seen in `direction`, and the sine of its angle to the pole.
+    fn lookup(&self, direction: Vec3) -> (usize, usize, f64) {
+        let d = unit_vector(self.to_map.transform_vector(direction));
+        let u = 0.5 + d.x.atan2(-d.z) / (2.0 * PI);
+        let v = d.y.clamp(-1.0, 1.0).acos() / PI;
+
+        let x = ((u * self.image.width as f64) as usize).min(self.image.width - 1);
+        let y = ((v * self.image.height as f64) as usize).min(self.image.height - 1);
+        let sin_theta = (1.0 - d.y * d.y).max(0.0).sqrt();
+        return (x, y, sin_theta);
+    }
+
+    pub fn value(&self, direction: Vec3) -> Color {
+        let (x, y, _) = self.lookup(direction);
+        return self.image.pixel(x, y) * self.intensity;
+    }
+
+    fn total(&self) -> f64 {
+        return *self.row_cdf.last().unwrap_or(&0.0);
+    }
+
+    // A black map has nothing to sample.
+    pub fn is_sampled(&self) -> bool {
+        return self.total() > 0.0;
+    }
+
+    // The density, per solid angle, with which `random` picks `direction`.
+    pub fn pdf_value(&self, direction: Vec3) -> f64 {
+        let (x, y, sin_theta) = self.lookup(direction);
+        if sin_theta <= 0.0 || !self.is_sampled() {
+            return 0.0;
+        }
+        let index = y * self.image.width + x;
+        let previous = if x == 0 {
+            0.0
+        } else {
+            self.column_cdf[index - 1]
+        };
+        let probability = (self.column_cdf[index] - previous) / self.total();
+
+        // Uniform within the pixel, whose size in (u, v) is 1 / (width *
+        // height), and d(solid angle) = 2 pi^2 sin(theta) du dv.
+        let pixel_count = (self.image.width * self.image.height) as f64;
+        return probability * pixel_count / (2.0 * PI * PI * sin_theta);
+    }
+
+    // A direction towards a pixel picked by its weight, then a uniformly
+    // random point inside that pixel.
+    pub fn random(&self, rng: &mut RandomNumberGenerator) -> Vec3 {
+        let width = self.image.width;
+        let target = rng.random_double() * self.total();
+        let y = self
+            .row_cdf
+            .partition_point(|sum| *sum <= target)
+            .min(self.image.height - 1);
+
+        let row = &self.column_cdf[y * width..(y + 1) * width];
+        let target = rng.random_double() * row[width - 1];
+        let x = row.partition_point(|sum| *sum <= target).min(width - 1);
+
+        let u = (x as f64 + rng.random_double()) / width as f64;
+        let v = (y as f64 + rng.random_double()) / self.image.height as f64;
+        let phi = (u - 0.5) * 2.0 * PI;
+        let theta = v * PI;
+        let d = Vec3 {
+            x: theta.sin() * phi.sin(),
+            y: theta.cos(),
+            z: -theta.sin() * phi.cos(),
+        };
+        return self.from_map.transform_vector(d);
+    }
+}
@@ -2,13 +2,15 @@ use super::material::*;
 use super::ray::*;
 use super::rng::*;
 use super::vec3::*;
-use std::rc::Rc;
+use std::sync::Arc;
 
 pub struct HitRecord {
     pub p: Point3,
     pub normal: Vec3,
-    pub material: Rc<dyn Material>,
+    pub material: Arc<dyn Material>,
     pub t: f64,
+    pub u: f64,
+    pub v: f64,
     pub front_face: bool,
 }
 
@@ -16,7 +18,7 @@ pub struct HitRecord {
 struct NullMaterial {}
 
 impl Material for NullMaterial {
-    fn diffuse(&self) -> Color {
+    fn diffuse(&self, _: &HitRecord) -> Color {
         return Color::default();
     }
 
@@ -37,8 +39,10 @@ impl Default for HitRecord {
         HitRecord {
             p: Point3::default(),
             normal: Vec3::default(),
-            material: Rc::new(NullMaterial {}) as Rc<dyn Material>,
+            material: Arc::new(NullMaterial {}) as Arc<dyn Material>,
             t: 0.0,
+            u: 0.0,
+            v: 0.0,
             front_face: false,
         }
     }
@@ -57,8 +61,10 @@ impl HitRecord {
                 y: 0.0,
                 z: 0.0,
             },
-            material: Rc::new(NullMaterial {}) as Rc<dyn Material>,
+            material: Arc::new(NullMaterial {}) as Arc<dyn Material>,
             t: 0.0,
+            u: 0.0,
+            v: 0.0,
             front_face: false,
         };
     }
@@ -78,6 +84,8 @@ impl HitRecord {
             normal: self.normal,
             material: self.material.clone(),
             t: self.t,
+            u: self.u,
+            v: self.v,
             front_face: self.front_face,
         };
     }
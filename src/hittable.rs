@@ -1,6 +1,39 @@
+use super::aabb::*;
 use super::hit_record::*;
 use super::ray::*;
+use super::rng::*;
+use super::vec3::*;
 
-pub trait Hittable {
-    fn hit(&self, r: Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool;
+pub trait Hittable: Send + Sync {
+    // The generator is only drawn from by volumes, which hit at random
+    // distances.
+    fn hit(
+        &self,
+        rng: &mut RandomNumberGenerator,
+        r: Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+    ) -> bool;
+    fn bounding_box(&self, output_box: &mut Aabb) -> bool;
+
+    // Light sampling, implemented by the shapes that can be lights: the
+    // density per solid angle with which random() picks `direction` from
+    // `origin`, and a random direction from `origin` towards the shape.
+    fn pdf_value(
+        &self,
+        _rng: &mut RandomNumberGenerator,
+        _origin: Point3,
+        _direction: Vec3,
+    ) -> f64 {
+        return 0.0;
+    }
+
+    fn random(&self, _rng: &mut RandomNumberGenerator, _origin: Point3) -> Vec3 {
+        return Vec3 {
+            x: 1.0,
+            y: 0.0,
+            z: 0.0,
+        };
+    }
 }
@@ -1,26 +1,36 @@
+use super::aabb::*;
 use super::hit_record::*;
 use super::hittable::*;
 use super::ray::*;
-use std::rc::Rc;
+use super::rng::*;
+use super::vec3::*;
+use std::sync::Arc;
 
 pub struct HittableList {
-    pub objects: Vec<Rc<dyn Hittable>>,
+    pub objects: Vec<Arc<dyn Hittable>>,
 }
 
 impl HittableList {
-    pub fn add(&mut self, object: Rc<dyn Hittable>) {
+    pub fn add(&mut self, object: Arc<dyn Hittable>) {
         self.objects.push(object);
     }
 }
 
 impl Hittable for HittableList {
-    fn hit(&self, r: Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+    fn hit(
+        &self,
+        rng: &mut RandomNumberGenerator,
+        r: Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+    ) -> bool {
         let mut temp_rec = HitRecord::new();
         let mut hit_anything = false;
         let mut closest_so_far = t_max;
 
         for object in self.objects.iter() {
-            if object.hit(r, t_min, closest_so_far, &mut temp_rec) {
+            if object.hit(rng, r, t_min, closest_so_far, &mut temp_rec) {
                 hit_anything = true;
                 closest_so_far = temp_rec.t;
                 *rec = temp_rec.clone();
@@ -29,4 +39,42 @@ impl Hittable for HittableList {
 
         return hit_anything;
     }
+
+    fn bounding_box(&self, output_box: &mut Aabb) -> bool {
+        if self.objects.is_empty() {
+            return false;
+        }
+
+        let mut temp_box = Aabb::default();
+        let mut result = Aabb::default();
+
+        for object in self.objects.iter() {
+            if !object.bounding_box(&mut temp_box) {
+                return false;
+            }
+            result = surrounding_box(result, temp_box);
+        }
+
+        *output_box = result;
+        return true;
+    }
+
+    // Picks one of the objects uniformly, so the density is their average.
+    fn pdf_value(&self, rng: &mut RandomNumberGenerator, origin: Point3, direction: Vec3) -> f64 {
+        if self.objects.is_empty() {
+            return 0.0;
+        }
+        let weight = 1.0 / self.objects.len() as f64;
+        let mut sum = 0.0;
+        for object in self.objects.iter() {
+            sum += weight * object.pdf_value(rng, origin, direction);
+        }
+        return sum;
+    }
+
+    fn random(&self, rng: &mut RandomNumberGenerator, origin: Point3) -> Vec3 {
+        let last = self.objects.len() as i32 - 1;
+        let index = rng.random_int(0, last).min(last) as usize;
+        return self.objects[index].random(rng, origin);
+    }
 }
@@ -0,0 +1,468 @@
+use super::vec3::*;
+use std::fs::{self, File};
+use std::io::BufReader;
+
+// A decoded image, rows top to bottom.
+pub struct ImageData {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Color>,
+}
+
+impl ImageData {
+    pub fn pixel(&self, x: usize, y: usize) -> Color {
+        return self.pixels[y * self.width + x];
+    }
+}
+
+// Loads a PNG or PPM (P3 or P6) image, chosen by the file extension. Stored
+// values are gamma decoded with the gamma of 2 the image writer encodes with,
+// so a texture renders with the colours it was saved with. The high dynamic
+// range Radiance .hdr and OpenEXR images hold linear radiance and are read
+// as they are.
+pub fn read_image(path: &str) -> Result<ImageData, String> {
+    let extension = path
+        .rsplit_once('.')
+        .map_or(String::new(), |(_, extension)| {
+            extension.to_ascii_lowercase()
+        });
+    let result = match extension.as_str() {
+        "png" => read_png(path),
+        "ppm" => read_ppm(path),
+        "hdr" => read_hdr(path),
+        "exr" => read_exr(path),
+        _ => Err(String::from(
+            "unsupported image format, expected a .png, .ppm, .hdr or .exr file",
+        )),
+    };
+    return result.map_err(|error| format!("{}: {}", path, error));
+}
+
+fn decode_gamma(value: f64, max_value: f64) -> f64 {
+    let value = value / max_value;
+    return value * value;
+}
+
+fn read_png(path: &str) -> Result<ImageData, String> {
+    let file = File::open(path).map_err(|error| error.to_string())?;
+    let mut decoder = png::Decoder::new(BufReader::new(file));
+    // Palettes, low bit depths and transparency are expanded to 8-bit
+    // channels, 16-bit images stay 16-bit.
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let mut reader = decoder.read_info().map_err(|error| error.to_string())?;
+
+    let size = reader
+        .output_buffer_size()
+        .ok_or(String::from("the image is too large"))?;
+    let mut buffer = vec![0; size];
+    let info = reader
+        .next_frame(&mut buffer)
+        .map_err(|error| error.to_string())?;
+
+    let channels = info.color_type.samples();
+    let (bytes_per_sample, max_value) = match info.bit_depth {
+        png::BitDepth::Sixteen => (2, 65535.0),
+        _ => (1, 255.0),
+    };
+    let sample = |offset: usize| -> f64 {
+        if bytes_per_sample == 2 {
+            return u16::from_be_bytes([buffer[offset], buffer[offset + 1]]) as f64;
+        }
+        return buffer[offset] as f64;
+    };
+
+    let width = info.width as usize;
+    let height = info.height as usize;
+    let mut pixels: Vec<Color> = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let offset = y * info.line_size + x * channels * bytes_per_sample;
+            // Grayscale images repeat the one channel, alpha is ignored.
+            let (r, g, b) = if channels < 3 {
+                let gray = sample(offset);
+                (gray, gray, gray)
+            } else {
+                (
+                    sample(offset),
+                    sample(offset + bytes_per_sample),
+                    sample(offset + 2 * bytes_per_sample),
+                )
+            };
+            pixels.push(Color {
+                x: decode_gamma(r, max_value),
+                y: decode_gamma(g, max_value),
+                z: decode_gamma(b, max_value),
+            });
+        }
+    }
+
+    return Ok(ImageData {
+        width,
+        height,
+        pixels,
+    });
+}
+
+fn read_ppm(path: &str) -> Result<ImageData, String> {
+    let data = fs::read(path).map_err(|error| error.to_string())?;
+    return parse_ppm(&data);
+}
+
+fn parse_ppm(data: &[u8]) -> Result<ImageData, String> {
+    // The header is four whitespace separated tokens, with comments running
+    // from '#' to the end of the line.
+    let mut position = 0;
+    let mut header: Vec<String> = vec![];
+    while header.len() < 4 {
+        while position < data.len()
+            && (data[position].is_ascii_whitespace() || data[position] == b'#')
+        {
+            if data[position] == b'#' {
+                while position < data.len() && data[position] != b'\n' {
+                    position += 1;
+                }
+            } else {
+                position += 1;
+            }
+        }
+        let start = position;
+        while position < data.len() && !data[position].is_ascii_whitespace() {
+            position += 1;
+        }
+        if start == position {
+            return Err(String::from("truncated PPM header"));
+        }
+        header.push(String::from_utf8_lossy(&data[start..position]).into_owned());
+    }
+
+    let number = |token: &str| -> Result<usize, String> {
+        return token
+            .parse::<usize>()
+            .map_err(|_| format!("malformed PPM header value '{}'", token));
+    };
+    let width = number(&header[1])?;
+    let height = number(&header[2])?;
+    let max_value = number(&header[3])?;
+    if width == 0 || height == 0 || max_value == 0 || max_value > 65535 {
+        return Err(String::from("malformed PPM header"));
+    }
+
+    // The sample count of a header claiming an absurd size would overflow.
+    let count = width
+        .checked_mul(height)
+        .and_then(|pixel_count| pixel_count.checked_mul(3))
+        .ok_or(String::from("malformed PPM header"))?;
+    let samples: Vec<f64> = match header[0].as_str() {
+        "P3" => {
+            let text = String::from_utf8_lossy(&data[position..]);
+            let samples: Result<Vec<f64>, String> = text
+                .split_whitespace()
+                .take(count)
+                .map(|token| number(token).map(|value| value as f64))
+                .collect();
+            samples?
+        }
+        "P6" => {
+            // A single whitespace character separates the header from the
+            // binary samples.
+            let start = position + 1;
+            let bytes_per_sample = if max_value > 255 { 2 } else { 1 };
+            let end = match count.checked_mul(bytes_per_sample) {
+                Some(size) if size <= data.len().saturating_sub(start) => start + size,
+                _ => return Err(String::from("truncated PPM image data")),
+            };
+            if bytes_per_sample == 2 {
+                data[start..end]
+                    .chunks_exact(2)
+                    .map(|pair| u16::from_be_bytes([pair[0], pair[1]]) as f64)
+                    .collect()
+            } else {
+                data[start..end].iter().map(|byte| *byte as f64).collect()
+            }
+        }
+        magic => {
+            return Err(format!(
+                "unsupported PPM type '{}', expected P3 or P6",
+                magic
+            ))
+        }
+    };
+    if samples.len() < count {
+        return Err(String::from("truncated PPM image data"));
+    }
+
+    let max_value = max_value as f64;
+    let pixels = samples
+        .chunks_exact(3)
+        .map(|rgb| Color {
+            x: decode_gamma(rgb[0], max_value),
+            y: decode_gamma(rgb[1], max_value),
+            z: decode_gamma(rgb[2], max_value),
+        })
+        .collect();
+
+    return Ok(ImageData {
+        width,
+        height,
+        pixels,
+    });
+}
+
+// Radiance RGBE pictures with flat or run length encoded scanlines, stored
+// top to bottom and left to right.
+fn read_hdr(path: &str) -> Result<ImageData, String> {
+    let data = fs::read(path).map_err(|error| error.to_string())?;
+    return parse_hdr(&data);
+}
+
+fn parse_hdr(data: &[u8]) -> Result<ImageData, String> {
+    // Header lines up to an empty one, then the resolution line.
+    let mut position = 0;
+    let mut next_line = || -> Option<String> {
+        let start = position;
+        while position < data.len() && data[position] != b'\n' {
+            position += 1;
+        }
+        if position >= data.len() {
+            return None;
+        }
+        position += 1;
+        return Some(String::from_utf8_lossy(&data[start..position - 1]).into_owned());
+    };
+    let magic = next_line().unwrap_or_default();
+    if !magic.starts_with("#?") {
+        return Err(String::from("not a Radiance picture"));
+    }
+    loop {
+        let line = next_line().ok_or(String::from("truncated Radiance header"))?;
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with("FORMAT=") && line != "FORMAT=32-bit_rle_rgbe" {
+            return Err(format!("unsupported Radiance format '{}'", &line[7..]));
+        }
+    }
+    let resolution = next_line().ok_or(String::from("truncated Radiance header"))?;
+    let (width, height) = match resolution.split_whitespace().collect::<Vec<&str>>()[..] {
+        ["-Y", height, "+X", width] => (
+            width.parse::<usize>().unwrap_or(0),
+            height.parse::<usize>().unwrap_or(0),
+        ),
+        _ => {
+            return Err(format!(
+                "unsupported Radiance resolution line '{}', expected -Y <height> +X <width>",
+                resolution
+            ))
+        }
+    };
+    let pixel_count = width
+        .checked_mul(height)
+        .filter(|pixel_count| *pixel_count > 0 && pixel_count.checked_mul(4).is_some())
+        .ok_or(String::from("malformed Radiance resolution line"))?;
+
+    let data = &data[position..];
+    let mut offset = 0;
+    // Only reserve what the file could plausibly hold, so that a bogus
+    // resolution fails as truncated data instead of exhausting memory.
+    let mut pixels: Vec<Color> = Vec::with_capacity(pixel_count.min(data.len()));
+    // Scanlines too wide to be run length encoded are stored flat, so the
+    // data must hold at least one of them before it is allocated.
+    if width >= 0x8000 && width * 4 > data.len() {
+        return Err(String::from("truncated Radiance image data"));
+    }
+    let mut scanline = vec![0u8; width * 4];
+    for _ in 0..height {
+        let truncated = || String::from("truncated Radiance image data");
+        let header = data.get(offset..offset + 4).ok_or_else(truncated)?;
+        let encoded = (8..0x8000).contains(&width)
+            && header[0] == 2
+            && header[1] == 2
+            && ((header[2] as usize) << 8 | header[3] as usize) == width;
+
+        if encoded {
+            // Each of the four components in turn, as runs of one repeated
+            // byte and stretches of literal bytes.
+            offset += 4;
+            for component in 0..4 {
+                let mut x = 0;
+                while x < width {
+                    let count = *data.get(offset).ok_or_else(truncated)? as usize;
+                    offset += 1;
+                    let (length, run) = if count > 128 {
+                        (count - 128, true)
+                    } else {
+                        (count, false)
+                    };
+                    if length == 0 || x + length > width {
+                        return Err(String::from("malformed Radiance scanline"));
+                    }
+                    for i in 0..length {
+                        let byte = if run { offset } else { offset + i };
+                        scanline[(x + i) * 4 + component] =
+                            *data.get(byte).ok_or_else(truncated)?;
+                    }
+                    offset += if run { 1 } else { length };
+                    x += length;
+                }
+            }
+        } else {
+            let flat = data.get(offset..offset + width * 4).ok_or_else(truncated)?;
+            scanline.copy_from_slice(flat);
+            offset += width * 4;
+        }
+
+        for rgbe in scanline.chunks_exact(4) {
+            pixels.push(from_rgbe(rgbe));
+        }
+    }
+
+    return Ok(ImageData {
+        width,
+        height,
+        pixels,
+    });
+}
+
+fn from_rgbe(rgbe: &[u8]) -> Color {
+    if rgbe[3] == 0 {
+        return Color {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        };
+    }
+    // The mantissas are fractions of 256, centered on their bucket.
+    let factor = 2f64.powi(rgbe[3] as i32 - 128 - 8);
+    return Color {
+        x: (rgbe[0] as f64 + 0.5) * factor,
+        y: (rgbe[1] as f64 + 0.5) * factor,
+        z: (rgbe[2] as f64 + 0.5) * factor,
+    };
+}
+
+// The RGB channels of the first layer of an OpenEXR image.
+fn read_exr(path: &str) -> Result<ImageData, String> {
+    let image = exr::prelude::read_first_rgba_layer_from_file(
+        path,
+        |resolution, _| ImageData {
+            width: resolution.width(),
+            height: resolution.height(),
+            pixels: vec![Color::default(); resolution.width() * resolution.height()],
+        },
+        |image: &mut ImageData, position, (r, g, b, _): (f32, f32, f32, f32)| {
+            let index = position.y() * image.width + position.x();
+            image.pixels[index] = Color {
+                x: r as f64,
+                y: g as f64,
+                z: b as f64,
+            };
+        },
+    )
+    .map_err(|error| error.to_string())?;
+
+    return Ok(image.layer_data.channel_data.pixels);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::image_writer::*;
+    use super::*;
+    use std::env;
+
+    fn error_of(result: Result<ImageData, String>) -> String {
+        return match result {
+            Ok(_) => panic!("expected an error"),
+            Err(message) => message,
+        };
+    }
+
+    fn rgb(r: f64, g: f64, b: f64) -> Color {
+        return Color { x: r, y: g, z: b };
+    }
+
+    // Compares the components relative to the largest one, as the shared
+    // exponent of RGBE only keeps that one precise.
+    fn assert_close(a: Color, b: Color, tolerance: f64) {
+        let largest = b.x.abs().max(b.y.abs()).max(b.z.abs());
+        for (a, b) in [(a.x, b.x), (a.y, b.y), (a.z, b.z)] {
+            assert!((a - b).abs() <= tolerance * largest, "{} != {}", a, b);
+        }
+    }
+
+    #[test]
+    fn ppm_headers_may_hold_comments() {
+        let image = parse_ppm(b"P3\n# made by hand\n2 # wide\n1\n255\n255 0 0 0 0 51\n").unwrap();
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_close(image.pixel(0, 0), rgb(1.0, 0.0, 0.0), 1e-12);
+        assert_close(image.pixel(1, 0), rgb(0.0, 0.0, 0.04), 1e-12);
+
+        let mut binary = b"P6 1 2 # rows\n# samples of two bytes\n65535\n".to_vec();
+        binary.extend_from_slice(&[255, 255, 0, 0, 0, 0, 0, 0, 255, 255, 0, 0]);
+        let image = parse_ppm(&binary).unwrap();
+        assert_eq!((image.width, image.height), (1, 2));
+        assert_close(image.pixel(0, 0), rgb(1.0, 0.0, 0.0), 1e-12);
+        assert_close(image.pixel(0, 1), rgb(0.0, 1.0, 0.0), 1e-12);
+    }
+
+    #[test]
+    fn ppm_sizes_that_overflow_are_malformed() {
+        let header = format!("P6 {} 2 255\n\0\0\0", usize::MAX);
+        assert_eq!(
+            error_of(parse_ppm(header.as_bytes())),
+            "malformed PPM header"
+        );
+        assert_eq!(
+            error_of(parse_ppm(b"P6 2 2 255\n\0\0\0")),
+            "truncated PPM image data"
+        );
+    }
+
+    #[test]
+    fn radiance_pictures_round_trip() {
+        let rows: Vec<Vec<Color>> = (0..3)
+            .map(|row| {
+                (0..4)
+                    .map(|col| rgb(0.01 + row as f64, 1000.0 * col as f64, 0.5))
+                    .collect()
+            })
+            .collect();
+        let path = env::temp_dir().join(format!("raytracer-test-{}.hdr", std::process::id()));
+        let path = path.to_string_lossy();
+        write_image(&path, OutputFormat::Hdr, 32, &rows, 1).unwrap();
+        let image = read_image(&path);
+        fs::remove_file(path.as_ref()).unwrap();
+        let image = image.unwrap();
+
+        // Rows are written bottom to top and read top to bottom.
+        assert_eq!((image.width, image.height), (4, 3));
+        for (row, colors) in rows.iter().enumerate() {
+            for (col, color) in colors.iter().enumerate() {
+                assert_close(image.pixel(col, 2 - row), *color, 1.0 / 128.0);
+            }
+        }
+    }
+
+    #[test]
+    fn radiance_scanlines_may_be_run_length_encoded() {
+        let mut data = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 8\n".to_vec();
+        data.extend_from_slice(&[2, 2, 0, 8]);
+        // A run of eight for red, literals for green, two runs of four for
+        // blue and a run of eight for the exponent.
+        data.extend_from_slice(&[128 + 8, 128]);
+        data.extend_from_slice(&[8, 0, 16, 32, 48, 64, 80, 96, 112]);
+        data.extend_from_slice(&[128 + 4, 0, 128 + 4, 255]);
+        data.extend_from_slice(&[128 + 8, 129]);
+        let image = parse_hdr(&data).unwrap();
+        assert_eq!((image.width, image.height), (8, 1));
+        assert_close(image.pixel(0, 0), rgb(1.0, 0.0, 0.0), 0.01);
+        assert_close(image.pixel(7, 0), rgb(1.0, 0.875, 2.0), 0.01);
+
+        let huge = b"#?RADIANCE\n\n-Y 4294967296 +X 4294967296\n";
+        assert_eq!(
+            error_of(parse_hdr(huge)),
+            "malformed Radiance resolution line"
+        );
+        let wide = b"#?RADIANCE\n\n-Y 1 +X 1000000000000\n";
+        assert_eq!(error_of(parse_hdr(wide)), "truncated Radiance image data");
+    }
+}
@@ -0,0 +1,252 @@
+use super::utils::*;
+use super::vec3::*;
+use exr::prelude::{f16, Image, SpecificChannels, Vec2, WritableImage};
+use std::fs::File;
+use std::io::{self, BufWriter, Cursor, Write};
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum OutputFormat {
+    PpmAscii,
+    Ppm,
+    Png,
+    Exr,
+    Hdr,
+    Pfm,
+}
+
+impl OutputFormat {
+    pub fn from_name(name: &str) -> Option<OutputFormat> {
+        return match name {
+            "ppm-ascii" => Some(OutputFormat::PpmAscii),
+            "ppm" => Some(OutputFormat::Ppm),
+            "png" => Some(OutputFormat::Png),
+            "exr" => Some(OutputFormat::Exr),
+            "hdr" => Some(OutputFormat::Hdr),
+            "pfm" => Some(OutputFormat::Pfm),
+            _ => None,
+        };
+    }
+
+    pub fn from_path(path: &str) -> Option<OutputFormat> {
+        let extension = path.rsplit_once('.')?.1.to_ascii_lowercase();
+        return OutputFormat::from_name(&extension);
+    }
+
+    // Supported bits per channel, the default first.
+    pub fn bit_depths(self) -> &'static [u8] {
+        return match self {
+            OutputFormat::PpmAscii => &[8],
+            OutputFormat::Ppm | OutputFormat::Png => &[8, 16],
+            OutputFormat::Exr => &[32, 16],
+            OutputFormat::Hdr | OutputFormat::Pfm => &[32],
+        };
+    }
+}
+
+// Writes the accumulated samples to `path`, or to stdout when it is "-".
+// Rows are stored bottom to top, as rendered. The low dynamic range formats
+// are gamma corrected and clamped, the high dynamic range ones (exr, hdr and
+// pfm) store the linear average radiance as is.
+pub fn write_image(
+    path: &str,
+    format: OutputFormat,
+    bit_depth: u8,
+    pixels: &[Vec<Color>],
+    samples_per_pixel: i32,
+) -> io::Result<()> {
+    let mut out: Box<dyn Write> = if path == "-" {
+        Box::new(BufWriter::new(io::stdout().lock()))
+    } else {
+        Box::new(BufWriter::new(File::create(path)?))
+    };
+
+    let scale = 1.0 / (samples_per_pixel as f64);
+    match format {
+        OutputFormat::PpmAscii => write_ppm_ascii(&mut out, pixels, scale)?,
+        OutputFormat::Ppm => write_ppm(&mut out, pixels, scale, bit_depth)?,
+        OutputFormat::Png => write_png(&mut out, pixels, scale, bit_depth)?,
+        OutputFormat::Exr => write_exr(&mut out, pixels, scale, bit_depth)?,
+        OutputFormat::Hdr => write_hdr(&mut out, pixels, scale)?,
+        OutputFormat::Pfm => write_pfm(&mut out, pixels, scale)?,
+    }
+    return out.flush();
+}
+
+fn image_size(pixels: &[Vec<Color>]) -> (usize, usize) {
+    return (pixels.first().map_or(0, |row| row.len()), pixels.len());
+}
+
+// Averages the samples, gamma corrects for gamma=2.0 and quantizes each
+// channel to `levels` steps.
+fn quantize(pixel_color: Color, scale: f64, levels: f64) -> [u32; 3] {
+    let r = (scale * pixel_color.x).sqrt();
+    let g = (scale * pixel_color.y).sqrt();
+    let b = (scale * pixel_color.z).sqrt();
+
+    let max = 1.0 - 0.25 / levels;
+    return [
+        (levels * clamp(r, 0.0, max)) as u32,
+        (levels * clamp(g, 0.0, max)) as u32,
+        (levels * clamp(b, 0.0, max)) as u32,
+    ];
+}
+
+// Packs the image top to bottom as 8-bit or big-endian 16-bit samples, the
+// layout shared by binary PPM and PNG.
+fn pack_samples(pixels: &[Vec<Color>], scale: f64, bit_depth: u8) -> Vec<u8> {
+    let (width, height) = image_size(pixels);
+    let bytes_per_sample = if bit_depth == 16 { 2 } else { 1 };
+    let mut data: Vec<u8> = Vec::with_capacity(width * height * 3 * bytes_per_sample);
+
+    for row in pixels.iter().rev() {
+        for pixel in row.iter() {
+            if bit_depth == 16 {
+                for channel in quantize(*pixel, scale, 65536.0) {
+                    data.extend_from_slice(&(channel as u16).to_be_bytes());
+                }
+            } else {
+                for channel in quantize(*pixel, scale, 256.0) {
+                    data.push(channel as u8);
+                }
+            }
+        }
+    }
+    return data;
+}
+
+fn write_ppm_ascii(out: &mut dyn Write, pixels: &[Vec<Color>], scale: f64) -> io::Result<()> {
+    let (width, height) = image_size(pixels);
+    writeln!(out, "P3\n{} {}\n255", width, height)?;
+    for row in pixels.iter().rev() {
+        for pixel in row.iter() {
+            let [r, g, b] = quantize(*pixel, scale, 256.0);
+            writeln!(out, "{} {} {}", r, g, b)?;
+        }
+    }
+    return Ok(());
+}
+
+fn write_ppm(
+    out: &mut dyn Write,
+    pixels: &[Vec<Color>],
+    scale: f64,
+    bit_depth: u8,
+) -> io::Result<()> {
+    let (width, height) = image_size(pixels);
+    let max_value = if bit_depth == 16 { 65535 } else { 255 };
+    write!(out, "P6\n{} {}\n{}\n", width, height, max_value)?;
+    return out.write_all(&pack_samples(pixels, scale, bit_depth));
+}
+
+fn write_png(
+    out: &mut dyn Write,
+    pixels: &[Vec<Color>],
+    scale: f64,
+    bit_depth: u8,
+) -> io::Result<()> {
+    let (width, height) = image_size(pixels);
+    let mut encoder = png::Encoder::new(out, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(if bit_depth == 16 {
+        png::BitDepth::Sixteen
+    } else {
+        png::BitDepth::Eight
+    });
+
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    writer
+        .write_image_data(&pack_samples(pixels, scale, bit_depth))
+        .map_err(io::Error::other)?;
+    return writer.finish().map_err(io::Error::other);
+}
+
+// OpenEXR with half or single precision float channels. The encoder needs to
+// seek, so the file is assembled in memory first.
+fn write_exr(
+    out: &mut dyn Write,
+    pixels: &[Vec<Color>],
+    scale: f64,
+    bit_depth: u8,
+) -> io::Result<()> {
+    let (width, height) = image_size(pixels);
+    let radiance = |position: Vec2<usize>| pixels[height - 1 - position.y()][position.x()] * scale;
+
+    let mut buffer = Cursor::new(Vec::new());
+    let result = if bit_depth == 16 {
+        let channels = SpecificChannels::rgb(|position: Vec2<usize>| {
+            let c = radiance(position);
+            (f16::from_f64(c.x), f16::from_f64(c.y), f16::from_f64(c.z))
+        });
+        Image::from_channels((width, height), channels)
+            .write()
+            .to_buffered(&mut buffer)
+    } else {
+        let channels = SpecificChannels::rgb(|position: Vec2<usize>| {
+            let c = radiance(position);
+            (c.x as f32, c.y as f32, c.z as f32)
+        });
+        Image::from_channels((width, height), channels)
+            .write()
+            .to_buffered(&mut buffer)
+    };
+    result.map_err(io::Error::other)?;
+
+    return out.write_all(buffer.get_ref());
+}
+
+// Shared exponent RGBE encoding of the Radiance picture format.
+fn to_rgbe(c: Color) -> [u8; 4] {
+    let max = c.x.max(c.y).max(c.z);
+    if max < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+
+    // max = mantissa * 2^exponent with mantissa in [0.5, 1)
+    let mut exponent = max.log2().floor() as i32 + 1;
+    let mut factor = 256.0 / 2f64.powi(exponent);
+    if max * factor >= 256.0 {
+        exponent += 1;
+        factor /= 2.0;
+    }
+    return [
+        (c.x.max(0.0) * factor) as u8,
+        (c.y.max(0.0) * factor) as u8,
+        (c.z.max(0.0) * factor) as u8,
+        (exponent + 128) as u8,
+    ];
+}
+
+// Radiance .hdr with flat, not run length encoded, scanlines.
+fn write_hdr(out: &mut dyn Write, pixels: &[Vec<Color>], scale: f64) -> io::Result<()> {
+    let (width, height) = image_size(pixels);
+    write!(
+        out,
+        "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+        height, width
+    )?;
+
+    let mut data: Vec<u8> = Vec::with_capacity(width * height * 4);
+    for row in pixels.iter().rev() {
+        for pixel in row.iter() {
+            data.extend_from_slice(&to_rgbe(*pixel * scale));
+        }
+    }
+    return out.write_all(&data);
+}
+
+// Portable float map, little endian, rows bottom to top like the framebuffer.
+fn write_pfm(out: &mut dyn Write, pixels: &[Vec<Color>], scale: f64) -> io::Result<()> {
+    let (width, height) = image_size(pixels);
+    write!(out, "PF\n{} {}\n-1.0\n", width, height)?;
+
+    let mut data: Vec<u8> = Vec::with_capacity(width * height * 12);
+    for row in pixels.iter() {
+        for pixel in row.iter() {
+            let c = *pixel * scale;
+            for channel in [c.x, c.y, c.z] {
+                data.extend_from_slice(&(channel as f32).to_le_bytes());
+            }
+        }
+    }
+    return out.write_all(&data);
+}
@@ -0,0 +1,88 @@
+use super::aabb::*;
+use super::hit_record::*;
+use super::hittable::*;
+use super::ray::*;
+use super::rng::*;
+use super::vec3::*;
+use std::sync::Arc;
+
+// Places a shared object in the world with an affine transform. Rays are
+// taken into object space by the inverse transform, hit points are brought
+// back by the transform and normals by its inverse transpose.
+pub struct Instance {
+    pub object: Arc<dyn Hittable>,
+    transform: Mat4,
+    inverse: Mat4,
+    normal_matrix: Mat4,
+}
+
+impl Instance {
+    // None when the transform is singular, for example a scale of zero.
+    pub fn create(object: Arc<dyn Hittable>, transform: Mat4) -> Option<Instance> {
+        let inverse = transform.inverse()?;
+        return Some(Instance {
+            object,
+            transform,
+            inverse,
+            normal_matrix: inverse.transpose(),
+        });
+    }
+}
+
+impl Hittable for Instance {
+    fn hit(
+        &self,
+        rng: &mut RandomNumberGenerator,
+        r: Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+    ) -> bool {
+        // The direction is not normalized, so t is the same in both spaces.
+        let object_ray = Ray {
+            origin: self.inverse.transform_point(r.origin),
+            direction: self.inverse.transform_vector(r.direction),
+            time: r.time,
+        };
+        if !self.object.hit(rng, object_ray, t_min, t_max, rec) {
+            return false;
+        }
+
+        // front_face carries over: transforming both the direction and the
+        // normal keeps the sign of their dot product.
+        rec.p = self.transform.transform_point(rec.p);
+        rec.normal = unit_vector(self.normal_matrix.transform_vector(rec.normal));
+        return true;
+    }
+
+    fn bounding_box(&self, output_box: &mut Aabb) -> bool {
+        let mut object_box = Aabb::default();
+        if !self.object.bounding_box(&mut object_box) {
+            return false;
+        }
+
+        let mut bbox = Aabb::default();
+        for corner in 0..8 {
+            let p = Point3 {
+                x: if corner & 1 == 0 {
+                    object_box.minimum.x
+                } else {
+                    object_box.maximum.x
+                },
+                y: if corner & 2 == 0 {
+                    object_box.minimum.y
+                } else {
+                    object_box.maximum.y
+                },
+                z: if corner & 4 == 0 {
+                    object_box.minimum.z
+                } else {
+                    object_box.maximum.z
+                },
+            };
+            bbox = surrounding_point(bbox, self.transform.transform_point(p));
+        }
+        *output_box = bbox;
+        return true;
+    }
+}
@@ -0,0 +1,449 @@
+use super::background::*;
+use super::delta_light::*;
+use super::hit_record::*;
+use super::hittable::*;
+use super::hittable_list::*;
+use super::ray::*;
+use super::rng::*;
+use super::vec3::*;
+use std::sync::Arc;
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum IntegratorKind {
+    Path,
+    Simple,
+}
+
+impl IntegratorKind {
+    pub fn from_name(name: &str) -> Option<IntegratorKind> {
+        return match name {
+            "path" => Some(IntegratorKind::Path),
+            "simple" => Some(IntegratorKind::Simple),
+            _ => None,
+        };
+    }
+}
+
+// Estimates the light arriving at the camera along a ray.
+pub trait Integrator: Send + Sync {
+    fn ray_color(&self, rng: &mut RandomNumberGenerator, r: Ray) -> Color;
+}
+
+// The recursive tracer of the book: every bounce follows the ray the
+// material scatters, and lights only count when a ray happens to hit them.
+// Light sampling and Russian roulette are not used, so delta lights stay
+// dark, and renders match the ones made before there were other integrators,
+// down to paths cut off at the maximum depth ending on white and absorbed
+// rays taking the material's diffuse color.
+pub struct SimpleIntegrator<'a> {
+    pub world: &'a dyn Hittable,
+    pub background: Background,
+    pub max_depth: i32,
+}
+
+impl SimpleIntegrator<'_> {
+    fn trace(&self, rng: &mut RandomNumberGenerator, r: Ray, depth: i32) -> Color {
+        if depth <= 0 {
+            return Color::default();
+        }
+        let mut rec = HitRecord::new();
+        if self.world.hit(rng, r, 0.001, f64::INFINITY, &mut rec) {
+            let emitted = rec.material.emitted(r, &rec);
+            let mut scattered = Ray::default();
+            let mut attenuation = Color::default();
+            if rec
+                .material
+                .scatter(rng, r, &rec, &mut attenuation, &mut scattered)
+            {
+                let recursed_color = self.trace(rng, scattered, depth - 1);
+                coz::progress!("scatter");
+                return emitted + attenuation * recursed_color;
+            }
+
+            coz::progress!("diffuse");
+            return emitted + rec.material.diffuse(&rec);
+        }
+
+        coz::progress!("sky");
+        return self.background.value(r);
+    }
+}
+
+impl Integrator for SimpleIntegrator<'_> {
+    fn ray_color(&self, rng: &mut RandomNumberGenerator, r: Ray) -> Color {
+        return self.trace(rng, r, self.max_depth);
+    }
+}
+
+// An iterative path tracer that samples the lights, the delta lights and an
+// environment map directly at every non-specular bounce, combines those
+// samples with the scattered rays by multiple importance sampling, and with
+// `roulette_depth` ends paths by Russian roulette after that many bounces.
+pub struct PathIntegrator<'a> {
+    pub world: &'a dyn Hittable,
+    pub lights: &'a HittableList,
+    pub delta_lights: &'a [Arc<dyn DeltaLight>],
+    pub background: Background,
+    pub max_depth: i32,
+    pub roulette_depth: Option<i32>,
+}
+
+impl PathIntegrator<'_> {
+    // Whether the hit found by `r` is on one of the lights.
+    fn hits_light(&self, rng: &mut RandomNumberGenerator, r: Ray, rec: &HitRecord) -> bool {
+        let mut light_rec = HitRecord::new();
+        if !self
+            .lights
+            .hit(rng, r, 0.001, f64::INFINITY, &mut light_rec)
+        {
+            return false;
+        }
+        return (light_rec.t - rec.t).abs() <= 1e-9 * rec.t.max(1.0);
+    }
+
+    // Next-event estimation: light arriving at `rec` straight from a point
+    // picked on one of the lights, when the shadow ray towards it is
+    // unobstructed. The sample is weighted against the material picking the
+    // same direction.
+    fn sample_lights(&self, rng: &mut RandomNumberGenerator, r_in: Ray, rec: &HitRecord) -> Color {
+        let black = Color {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        };
+
+        let direction = self.lights.random(rng, rec.p);
+        let pdf = self.lights.pdf_value(rng, rec.p, direction);
+        if pdf <= 0.0 {
+            return black;
+        }
+        let f = rec.material.eval(r_in, rec, direction);
+        if near_zero(f) {
+            return black;
+        }
+
+        let shadow_ray = Ray {
+            origin: rec.p,
+            direction,
+            time: r_in.time,
+        };
+        let mut light_rec = HitRecord::new();
+        if !self
+            .world
+            .hit(rng, shadow_ray, 0.001, f64::INFINITY, &mut light_rec)
+            || !self.hits_light(rng, shadow_ray, &light_rec)
+        {
+            return black;
+        }
+        let weight = power_heuristic(pdf, rec.material.pdf(r_in, rec, direction));
+        return f * light_rec.material.emitted(shadow_ray, &light_rec) * (weight / pdf);
+    }
+
+    // Like `sample_lights`, for a direction picked on the environment map,
+    // which is seen when the shadow ray leaves the scene.
+    fn sample_background(
+        &self,
+        rng: &mut RandomNumberGenerator,
+        r_in: Ray,
+        rec: &HitRecord,
+    ) -> Color {
+        let black = Color {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        };
+
+        let direction = self.background.random(rng);
+        let pdf = self.background.pdf_value(direction);
+        if pdf <= 0.0 {
+            return black;
+        }
+        let f = rec.material.eval(r_in, rec, direction);
+        if near_zero(f) {
+            return black;
+        }
+
+        let shadow_ray = Ray {
+            origin: rec.p,
+            direction,
+            time: r_in.time,
+        };
+        let mut occluder = HitRecord::new();
+        if self
+            .world
+            .hit(rng, shadow_ray, 0.001, f64::INFINITY, &mut occluder)
+        {
+            return black;
+        }
+        let weight = power_heuristic(pdf, rec.material.pdf(r_in, rec, direction));
+        return f * self.background.value(shadow_ray) * (weight / pdf);
+    }
+
+    // Light from every delta light that is not in shadow. Nothing else can
+    // find these lights, so there is nothing to weight against.
+    fn sample_delta_lights(
+        &self,
+        rng: &mut RandomNumberGenerator,
+        r_in: Ray,
+        rec: &HitRecord,
+    ) -> Color {
+        let mut color = Color {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        };
+        for light in self.delta_lights {
+            let sample = match light.illuminate(rec.p) {
+                Some(sample) => sample,
+                None => continue,
+            };
+            let f = rec.material.eval(r_in, rec, sample.direction);
+            if near_zero(f) {
+                continue;
+            }
+
+            let shadow_ray = Ray {
+                origin: rec.p,
+                direction: sample.direction,
+                time: r_in.time,
+            };
+            let mut occluder = HitRecord::new();
+            if self.world.hit(
+                rng,
+                shadow_ray,
+                0.001,
+                sample.distance - 0.001,
+                &mut occluder,
+            ) {
+                continue;
+            }
+            color.add_assign(f * sample.irradiance);
+        }
+        return color;
+    }
+}
+
+impl Integrator for PathIntegrator<'_> {
+    fn ray_color(&self, rng: &mut RandomNumberGenerator, r: Ray) -> Color {
+        let mut color = Color {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        };
+        // The fraction of the light found by `r` that reaches the camera.
+        let mut throughput = Color {
+            x: 1.0,
+            y: 1.0,
+            z: 1.0,
+        };
+        let mut r = r;
+        // The density with which the previous bounce picked the direction of
+        // `r` when it also sampled the lights directly. A light or the
+        // environment map found by `r` is then weighted against that sample.
+        let mut scatter_pdf: Option<f64> = None;
+        let sample_background = self.background.is_sampled();
+        let sample_lights = !self.lights.objects.is_empty();
+        let sample_delta_lights = !self.delta_lights.is_empty();
+
+        for bounce in 0..self.max_depth {
+            let mut rec = HitRecord::new();
+            if !self.world.hit(rng, r, 0.001, f64::INFINITY, &mut rec) {
+                let mut sky = self.background.value(r);
+                if let Some(scatter_pdf) = scatter_pdf.filter(|_| sample_background) {
+                    let background_pdf = self.background.pdf_value(r.direction);
+                    sky = sky * power_heuristic(scatter_pdf, background_pdf);
+                }
+                coz::progress!("sky");
+                return color + throughput * sky;
+            }
+
+            let mut emitted = rec.material.emitted(r, &rec);
+            if let Some(scatter_pdf) = scatter_pdf {
+                if self.hits_light(rng, r, &rec) {
+                    let light_pdf = self.lights.pdf_value(rng, r.origin, r.direction);
+                    emitted = emitted * power_heuristic(scatter_pdf, light_pdf);
+                }
+            }
+            color.add_assign(throughput * emitted);
+
+            let mut scattered = Ray::default();
+            let mut attenuation = Color::default();
+            let scatters = rec
+                .material
+                .scatter(rng, r, &rec, &mut attenuation, &mut scattered);
+
+            // Scenes without lights or an environment map draw no extra
+            // random numbers. The lights are sampled even when the material
+            // absorbs the scattered ray, as a glossy one does when it points
+            // below the surface, or that light would go missing.
+            scatter_pdf = None;
+            if !rec.material.is_specular() {
+                if sample_lights {
+                    color.add_assign(throughput * self.sample_lights(rng, r, &rec));
+                }
+                if sample_background {
+                    color.add_assign(throughput * self.sample_background(rng, r, &rec));
+                }
+                if scatters && (sample_lights || sample_background) {
+                    scatter_pdf = Some(rec.material.pdf(r, &rec, scattered.direction));
+                }
+                if sample_delta_lights {
+                    color.add_assign(throughput * self.sample_delta_lights(rng, r, &rec));
+                }
+            }
+
+            // An absorbed ray carries no light, unlike in the simple
+            // integrator, where it takes the material's diffuse color.
+            if !scatters {
+                coz::progress!("diffuse");
+                return color;
+            }
+
+            // Russian roulette: a path survives with a probability that
+            // follows the light it carries, and the survivors make up for
+            // the ended ones, so the estimate stays unbiased.
+            throughput = throughput * attenuation;
+            if self.roulette_depth.is_some_and(|depth| bounce >= depth) {
+                let survival = throughput.x.max(throughput.y).max(throughput.z).min(1.0);
+                if rng.random_double() >= survival {
+                    return color;
+                }
+                throughput = throughput / survival;
+            }
+
+            r = scattered;
+            coz::progress!("scatter");
+        }
+
+        // A path cut off at the maximum depth adds nothing more, where the
+        // simple integrator ends it on white.
+        return color;
+    }
+}
+
+// Weight of a sample taken with density `pdf` when `other_pdf` is the density
+// of the other strategy for the same direction.
+fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+    let pdf_squared = pdf * pdf;
+    let sum = pdf_squared + other_pdf * other_pdf;
+    if sum <= 0.0 {
+        return 0.0;
+    }
+    return pdf_squared / sum;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::aarect::*;
+    use super::super::diffuse_light::*;
+    use super::super::lambertian::*;
+    use super::super::texture::*;
+    use super::*;
+
+    fn mean_color(
+        integrator: &dyn Integrator,
+        rng: &RandomNumberGenerator,
+        r: Ray,
+        samples: u64,
+    ) -> Color {
+        let mut sum = Color {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        };
+        for sample in 0..samples {
+            sum.add_assign(integrator.ray_color(&mut rng.for_sample(0, sample), r));
+        }
+        return sum / samples as f64;
+    }
+
+    // A grey floor lit only by a small rectangle light above it. The simple
+    // integrator finds the light by chance, the path integrator mostly by
+    // sampling it and weighting both strategies, and both estimate the same
+    // light. No path is long enough to be cut off, so the white that ends
+    // paths in the simple integrator does not show.
+    #[test]
+    fn both_integrators_converge_to_the_same_light() {
+        let light = Arc::new(XzRect {
+            x0: -0.5,
+            x1: 0.5,
+            z0: -0.5,
+            z1: 0.5,
+            k: 2.0,
+            material: Arc::new(DiffuseLight {
+                emit: Color {
+                    x: 4.0,
+                    y: 4.0,
+                    z: 4.0,
+                },
+            }),
+        });
+        let floor = Arc::new(XzRect {
+            x0: -5.0,
+            x1: 5.0,
+            z0: -5.0,
+            z1: 5.0,
+            k: 0.0,
+            material: Arc::new(Lambertian {
+                albedo: Arc::new(SolidColor {
+                    color: Color {
+                        x: 0.5,
+                        y: 0.5,
+                        z: 0.5,
+                    },
+                }),
+            }),
+        });
+        let world = HittableList {
+            objects: vec![floor, light.clone()],
+        };
+        let lights = HittableList {
+            objects: vec![light],
+        };
+        let black = Background::Solid(Color {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        });
+        let simple = SimpleIntegrator {
+            world: &world,
+            background: black.clone(),
+            max_depth: 50,
+        };
+        let path = PathIntegrator {
+            world: &world,
+            lights: &lights,
+            delta_lights: &[],
+            background: black,
+            max_depth: 50,
+            roulette_depth: None,
+        };
+
+        let rng = RandomNumberGenerator::create(90211, RngAlgorithm::Pcg32);
+        for target in [0.0, 0.4, 1.5] {
+            let r = Ray {
+                origin: Point3 {
+                    x: 0.0,
+                    y: 1.0,
+                    z: 3.0,
+                },
+                direction: Vec3 {
+                    x: target,
+                    y: -1.0,
+                    z: -3.0,
+                },
+                time: 0.0,
+            };
+            let simple_mean = mean_color(&simple, &rng, r, 100000);
+            let path_mean = mean_color(&path, &rng, r, 20000);
+            assert!(path_mean.x > 0.0);
+            assert!(
+                (simple_mean.x - path_mean.x).abs() < 0.05 * path_mean.x,
+                "{}: simple {} path {}",
+                target,
+                simple_mean.x,
+                path_mean.x
+            );
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use super::hit_record::*;
+use super::material::*;
+use super::ray::*;
+use super::rng::*;
+use super::texture::*;
+use super::vec3::*;
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+// Phase function of a participating medium: scatters equally in all
+// directions.
+pub struct Isotropic {
+    pub albedo: Arc<dyn Texture>,
+}
+
+impl Material for Isotropic {
+    fn diffuse(&self, rec: &HitRecord) -> Color {
+        return self.albedo.value(rec.u, rec.v, rec.p);
+    }
+
+    fn scatter(
+        &self,
+        rng: &mut RandomNumberGenerator,
+        r_in: Ray,
+        rec: &HitRecord,
+        attenuation: &mut Color,
+        scattered: &mut Ray,
+    ) -> bool {
+        *scattered = Ray {
+            origin: rec.p,
+            direction: rng.random_unit_vector(),
+            time: r_in.time,
+        };
+        *attenuation = self.albedo.value(rec.u, rec.v, rec.p);
+        return true;
+    }
+
+    fn is_specular(&self) -> bool {
+        return false;
+    }
+
+    fn eval(&self, _: Ray, rec: &HitRecord, _: Vec3) -> Color {
+        return self.albedo.value(rec.u, rec.v, rec.p) / (4.0 * PI);
+    }
+
+    fn pdf(&self, _: Ray, _: &HitRecord, _: Vec3) -> f64 {
+        return 1.0 / (4.0 * PI);
+    }
+}
@@ -2,22 +2,24 @@ use super::hit_record::*;
 use super::material::*;
 use super::ray::*;
 use super::rng::*;
+use super::texture::*;
 use super::vec3::*;
+use std::f64::consts::PI;
+use std::sync::Arc;
 
-#[derive(Copy, Clone)]
 pub struct Lambertian {
-    pub albedo: Color,
+    pub albedo: Arc<dyn Texture>,
 }
 
 impl Material for Lambertian {
-    fn diffuse(&self) -> Color {
-        return self.albedo;
+    fn diffuse(&self, rec: &HitRecord) -> Color {
+        return self.albedo.value(rec.u, rec.v, rec.p);
     }
 
     fn scatter(
         &self,
         rng: &mut RandomNumberGenerator,
-        _: Ray,
+        r_in: Ray,
         rec: &HitRecord,
         attenuation: &mut Color,
         scattered: &mut Ray,
@@ -32,8 +34,23 @@ impl Material for Lambertian {
         *scattered = Ray {
             origin: rec.p,
             direction: scatter_direction,
+            time: r_in.time,
         };
-        *attenuation = self.albedo;
+        *attenuation = self.albedo.value(rec.u, rec.v, rec.p);
         return true;
     }
+
+    fn is_specular(&self) -> bool {
+        return false;
+    }
+
+    fn eval(&self, _: Ray, rec: &HitRecord, direction: Vec3) -> Color {
+        let cosine = dot(rec.normal, unit_vector(direction)).max(0.0);
+        return self.albedo.value(rec.u, rec.v, rec.p) * (cosine / PI);
+    }
+
+    // The scattered directions are cosine distributed.
+    fn pdf(&self, _: Ray, rec: &HitRecord, direction: Vec3) -> f64 {
+        return dot(rec.normal, unit_vector(direction)).max(0.0) / PI;
+    }
 }
@@ -1,45 +1,93 @@
+#![allow(clippy::needless_return)]
+
+mod aabb;
+mod aarect;
+mod background;
+mod bvh;
 mod camera;
+mod conductor;
+mod constant_medium;
+mod cuboid;
+mod delta_light;
 mod dielectric;
+mod diffuse_light;
+mod environment_map;
 mod hit_record;
 mod hittable;
 mod hittable_list;
+mod image_reader;
+mod image_writer;
+mod instance;
+mod integrator;
+mod isotropic;
 mod lambertian;
 mod material;
+mod mesh;
 mod metal;
+mod microfacet;
+mod moving;
+mod moving_sphere;
+mod obj_loader;
+mod onb;
+mod options;
+mod perlin;
+mod quad;
 mod ray;
 mod rng;
+mod rough_dielectric;
+mod scene;
+mod scene_file;
+mod sky;
 mod sphere;
+mod texture;
 mod utils;
 mod vec3;
 
+use aarect::*;
+use background::*;
+use bvh::*;
 use camera::*;
+use cuboid::*;
 use dielectric::*;
-use hit_record::*;
-use hittable::*;
+use diffuse_light::*;
 use hittable_list::*;
+use image_writer::*;
+use instance::*;
+use integrator::*;
 use lambertian::*;
 use material::*;
 use metal::*;
-use ray::*;
-// use rayon::prelude::*;
+use options::*;
+use perlin::*;
+use rayon::prelude::*;
 use rng::*;
+use scene::*;
+use scene_file::*;
 use sphere::*;
-use std::{env, f64::INFINITY, rc::Rc};
-use utils::*;
+use std::{
+    env, process,
+    sync::{
+        atomic::{AtomicI32, Ordering},
+        Arc,
+    },
+};
+use texture::*;
 use vec3::*;
 
 fn simple_scene() -> HittableList {
     let mut world = HittableList { objects: vec![] };
 
-    let ground_material: Rc<dyn Material> = Rc::new(Lambertian {
-        albedo: Color {
-            x: 0.5,
-            y: 0.5,
-            z: 0.5,
-        },
+    let ground_material: Arc<dyn Material> = Arc::new(Lambertian {
+        albedo: Arc::new(SolidColor {
+            color: Color {
+                x: 0.5,
+                y: 0.5,
+                z: 0.5,
+            },
+        }),
     });
 
-    world.add(Rc::new(Sphere {
+    world.add(Arc::new(Sphere {
         center: Point3 {
             x: 0.0,
             y: -1000.0,
@@ -49,8 +97,8 @@ fn simple_scene() -> HittableList {
         material: ground_material,
     }));
 
-    let material1: Rc<dyn Material> = Rc::new(Dielectric { ir: 1.5 });
-    world.add(Rc::new(Sphere {
+    let material1: Arc<dyn Material> = Arc::new(Dielectric::clear(1.5));
+    world.add(Arc::new(Sphere {
         center: Point3 {
             x: 0.0,
             y: 1.0,
@@ -60,14 +108,16 @@ fn simple_scene() -> HittableList {
         material: material1,
     }));
 
-    let material2: Rc<dyn Material> = Rc::new(Lambertian {
-        albedo: Color {
-            x: 0.4,
-            y: 0.2,
-            z: 0.1,
-        },
+    let material2: Arc<dyn Material> = Arc::new(Lambertian {
+        albedo: Arc::new(SolidColor {
+            color: Color {
+                x: 0.4,
+                y: 0.2,
+                z: 0.1,
+            },
+        }),
     });
-    world.add(Rc::new(Sphere {
+    world.add(Arc::new(Sphere {
         center: Point3 {
             x: -4.0,
             y: 1.0,
@@ -77,15 +127,17 @@ fn simple_scene() -> HittableList {
         material: material2,
     }));
 
-    let material3: Rc<dyn Material> = Rc::new(Metal {
-        albedo: Color {
-            x: 0.7,
-            y: 0.6,
-            z: 0.5,
-        },
+    let material3: Arc<dyn Material> = Arc::new(Metal {
+        albedo: Arc::new(SolidColor {
+            color: Color {
+                x: 0.7,
+                y: 0.6,
+                z: 0.5,
+            },
+        }),
         fuzz: 0.0,
     });
-    world.add(Rc::new(Sphere {
+    world.add(Arc::new(Sphere {
         center: Point3 {
             x: 4.0,
             y: 1.0,
@@ -98,18 +150,45 @@ fn simple_scene() -> HittableList {
     return world;
 }
 
-fn random_scene(rng: &mut RandomNumberGenerator) -> HittableList {
+// Names the random stream of the Perlin noise in the stone variant of the
+// random scene.
+const NOISE_STREAM: u64 = 0x6e6f697365;
+
+// With `noise`, the stone variant: a marble ground and a wooden sphere in
+// place of the flat coloured ones. The sphere layout is the same either way.
+fn random_scene(rng: &mut RandomNumberGenerator, noise: Option<Arc<Perlin>>) -> HittableList {
     let mut world = HittableList { objects: vec![] };
 
-    let ground_material: Rc<dyn Material> = Rc::new(Lambertian {
-        albedo: Color {
-            x: 0.5,
-            y: 0.5,
-            z: 0.5,
-        },
+    let ground_texture: Arc<dyn Texture> = match &noise {
+        Some(noise) => Arc::new(NoiseTexture {
+            noise: noise.clone(),
+            pattern: NoisePattern::Marble,
+            scale: 1.5,
+            octaves: 7,
+            low: Color {
+                x: 0.2,
+                y: 0.2,
+                z: 0.22,
+            },
+            high: Color {
+                x: 0.8,
+                y: 0.78,
+                z: 0.75,
+            },
+        }),
+        None => Arc::new(SolidColor {
+            color: Color {
+                x: 0.5,
+                y: 0.5,
+                z: 0.5,
+            },
+        }),
+    };
+    let ground_material: Arc<dyn Material> = Arc::new(Lambertian {
+        albedo: ground_texture,
     });
 
-    world.add(Rc::new(Sphere {
+    world.add(Arc::new(Sphere {
         center: Point3 {
             x: 0.0,
             y: -1000.0,
@@ -135,22 +214,27 @@ fn random_scene(rng: &mut RandomNumberGenerator) -> HittableList {
             };
 
             if (center - world_center).length() > 0.9 {
-                let material: Rc<dyn Material>;
+                let material: Arc<dyn Material>;
                 let choose_mat = rng.random_double();
                 if choose_mat < 0.8 {
                     // diffuse
                     let albedo = rng.random_vec3();
-                    material = Rc::new(Lambertian { albedo });
+                    material = Arc::new(Lambertian {
+                        albedo: Arc::new(SolidColor { color: albedo }),
+                    });
                 } else if choose_mat < 0.95 {
                     // metal
                     let albedo = rng.random_vec3_range(0.5, 1.0);
                     let fuzz = rng.random_range(0.0, 0.5);
-                    material = Rc::new(Metal { albedo, fuzz });
+                    material = Arc::new(Metal {
+                        albedo: Arc::new(SolidColor { color: albedo }),
+                        fuzz,
+                    });
                 } else {
                     // glass
-                    material = Rc::new(Dielectric { ir: 1.5 });
+                    material = Arc::new(Dielectric::clear(1.5));
                 }
-                world.add(Rc::new(Sphere {
+                world.add(Arc::new(Sphere {
                     center,
                     radius,
                     material,
@@ -159,8 +243,8 @@ fn random_scene(rng: &mut RandomNumberGenerator) -> HittableList {
         }
     }
 
-    let material1: Rc<dyn Material> = Rc::new(Dielectric { ir: 1.5 });
-    world.add(Rc::new(Sphere {
+    let material1: Arc<dyn Material> = Arc::new(Dielectric::clear(1.5));
+    world.add(Arc::new(Sphere {
         center: Point3 {
             x: 0.0,
             y: 1.0,
@@ -170,14 +254,33 @@ fn random_scene(rng: &mut RandomNumberGenerator) -> HittableList {
         material: material1,
     }));
 
-    let material2: Rc<dyn Material> = Rc::new(Lambertian {
-        albedo: Color {
-            x: 0.4,
-            y: 0.2,
-            z: 0.1,
-        },
-    });
-    world.add(Rc::new(Sphere {
+    let texture2: Arc<dyn Texture> = match &noise {
+        Some(noise) => Arc::new(NoiseTexture {
+            noise: noise.clone(),
+            pattern: NoisePattern::Wood,
+            scale: 4.0,
+            octaves: 4,
+            low: Color {
+                x: 0.3,
+                y: 0.15,
+                z: 0.06,
+            },
+            high: Color {
+                x: 0.6,
+                y: 0.38,
+                z: 0.18,
+            },
+        }),
+        None => Arc::new(SolidColor {
+            color: Color {
+                x: 0.4,
+                y: 0.2,
+                z: 0.1,
+            },
+        }),
+    };
+    let material2: Arc<dyn Material> = Arc::new(Lambertian { albedo: texture2 });
+    world.add(Arc::new(Sphere {
         center: Point3 {
             x: -4.0,
             y: 1.0,
@@ -187,15 +290,17 @@ fn random_scene(rng: &mut RandomNumberGenerator) -> HittableList {
         material: material2,
     }));
 
-    let material3: Rc<dyn Material> = Rc::new(Metal {
-        albedo: Color {
-            x: 0.7,
-            y: 0.6,
-            z: 0.5,
-        },
+    let material3: Arc<dyn Material> = Arc::new(Metal {
+        albedo: Arc::new(SolidColor {
+            color: Color {
+                x: 0.7,
+                y: 0.6,
+                z: 0.5,
+            },
+        }),
         fuzz: 0.0,
     });
-    world.add(Rc::new(Sphere {
+    world.add(Arc::new(Sphere {
         center: Point3 {
             x: 4.0,
             y: 1.0,
@@ -208,97 +313,201 @@ fn random_scene(rng: &mut RandomNumberGenerator) -> HittableList {
     return world;
 }
 
-fn ray_color(
-    row: usize,
-    col: usize,
-    r: Ray,
-    world: &HittableList,
-    depth: i32,
-    rng: &mut RandomNumberGenerator,
-) -> Color {
-    if depth <= 0 {
-        return Color::default();
-    }
-    let mut rec = HitRecord::new();
-    if world.hit(r, 0.001, INFINITY, &mut rec) {
-        let mut scattered = Ray::default();
-        let mut attenuation = Color::default();
-        if rec
-            .material
-            .scatter(rng, r, &rec, &mut attenuation, &mut scattered)
-        {
-            let recursed_color = ray_color(row, col, scattered, world, depth - 1, rng);
-            // eprintln!(
-            //     "> Scatter {} {} dir={} attenuation={} recursed_color={} out={}",
-            //     col,
-            //     row,
-            //     r.direction,
-            //     attenuation,
-            //     recursed_color,
-            //     attenuation * recursed_color
-            // );
-            coz::progress!("scatter");
-            return attenuation * recursed_color;
-        }
-
-        // eprintln!("> Diffuse {} {} = {}", col, row, rec.material.diffuse());
-        coz::progress!("diffuse");
-        return rec.material.diffuse();
-    }
-
-    let unit_direction = unit_vector(r.direction);
-    let t = 0.5 * (unit_direction.y + 1.0);
-    let white = Color {
-        x: 1.0,
-        y: 1.0,
-        z: 1.0,
-    };
-    let blue = Color {
-        x: 0.5,
-        y: 0.7,
-        z: 1.0,
-    };
-    let sky = white * (1.0 - t) + blue * t;
-
-    // eprintln!("> Sky {} {} = {}", col, row, sky);
-    coz::progress!("sky");
-    return sky;
-}
+fn cornell_box_scene() -> Scene {
+    let mut world = HittableList { objects: vec![] };
 
-fn write_color(pixel_color: Color, samples_per_pixel: i32) {
-    let scale = 1.0 / (samples_per_pixel as f64);
+    let red: Arc<dyn Material> = Arc::new(Lambertian {
+        albedo: Arc::new(SolidColor {
+            color: Color {
+                x: 0.65,
+                y: 0.05,
+                z: 0.05,
+            },
+        }),
+    });
+    let white: Arc<dyn Material> = Arc::new(Lambertian {
+        albedo: Arc::new(SolidColor {
+            color: Color {
+                x: 0.73,
+                y: 0.73,
+                z: 0.73,
+            },
+        }),
+    });
+    let green: Arc<dyn Material> = Arc::new(Lambertian {
+        albedo: Arc::new(SolidColor {
+            color: Color {
+                x: 0.12,
+                y: 0.45,
+                z: 0.15,
+            },
+        }),
+    });
+    let light: Arc<dyn Material> = Arc::new(DiffuseLight {
+        emit: Color {
+            x: 15.0,
+            y: 15.0,
+            z: 15.0,
+        },
+    });
 
-    let r = (scale * pixel_color.x).sqrt();
-    let g = (scale * pixel_color.y).sqrt();
-    let b = (scale * pixel_color.z).sqrt();
+    world.add(Arc::new(YzRect {
+        y0: 0.0,
+        y1: 555.0,
+        z0: 0.0,
+        z1: 555.0,
+        k: 555.0,
+        material: green,
+    }));
+    world.add(Arc::new(YzRect {
+        y0: 0.0,
+        y1: 555.0,
+        z0: 0.0,
+        z1: 555.0,
+        k: 0.0,
+        material: red,
+    }));
+    let light = Arc::new(XzRect {
+        x0: 213.0,
+        x1: 343.0,
+        z0: 227.0,
+        z1: 332.0,
+        k: 554.0,
+        material: light,
+    });
+    world.add(light.clone());
+    world.add(Arc::new(XzRect {
+        x0: 0.0,
+        x1: 555.0,
+        z0: 0.0,
+        z1: 555.0,
+        k: 0.0,
+        material: white.clone(),
+    }));
+    world.add(Arc::new(XzRect {
+        x0: 0.0,
+        x1: 555.0,
+        z0: 0.0,
+        z1: 555.0,
+        k: 555.0,
+        material: white.clone(),
+    }));
+    world.add(Arc::new(XyRect {
+        x0: 0.0,
+        x1: 555.0,
+        y0: 0.0,
+        y1: 555.0,
+        k: 555.0,
+        material: white.clone(),
+    }));
 
-    let ri = (256.0 * clamp(r, 0.0, 0.999)) as i32;
-    let gi = (256.0 * clamp(g, 0.0, 0.999)) as i32;
-    let bi = (256.0 * clamp(b, 0.0, 0.999)) as i32;
+    let box1 = Arc::new(Cuboid::create(
+        Point3 {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        },
+        Point3 {
+            x: 165.0,
+            y: 330.0,
+            z: 165.0,
+        },
+        white.clone(),
+    ));
+    let transform1 = Mat4::translation(Vec3 {
+        x: 265.0,
+        y: 0.0,
+        z: 295.0,
+    }) * Mat4::rotation(
+        Vec3 {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        },
+        15.0,
+    );
+    world.add(Arc::new(Instance::create(box1, transform1).unwrap()));
 
-    // eprintln!(
-    //     "write_color {} r {:+.4} g {:+.4} b {:+.4} ri {} gi {} bi {}",
-    //     pixel_color, r, g, b, ri, gi, bi
-    // );
+    let box2 = Arc::new(Cuboid::create(
+        Point3 {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        },
+        Point3 {
+            x: 165.0,
+            y: 165.0,
+            z: 165.0,
+        },
+        white,
+    ));
+    let transform2 = Mat4::translation(Vec3 {
+        x: 130.0,
+        y: 0.0,
+        z: 65.0,
+    }) * Mat4::rotation(
+        Vec3 {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        },
+        -18.0,
+    );
+    world.add(Arc::new(Instance::create(box2, transform2).unwrap()));
 
-    println!("{} {} {}", ri, gi, bi);
+    return Scene {
+        world,
+        lights: HittableList {
+            objects: vec![light],
+        },
+        delta_lights: vec![],
+        camera: CameraSettings {
+            lookfrom: Point3 {
+                x: 278.0,
+                y: 278.0,
+                z: -800.0,
+            },
+            lookat: Point3 {
+                x: 278.0,
+                y: 278.0,
+                z: 0.0,
+            },
+            vup: Vec3 {
+                x: 0.0,
+                y: 1.0,
+                z: 0.0,
+            },
+            vfov: 40.0,
+            aperture: 0.0,
+            focus_dist: 10.0,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
+        },
+        settings: RenderSettings {
+            image_width: 600,
+            image_height: 600,
+            ..RenderSettings::default()
+        },
+        background: Background::Solid(Color {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        }),
+    };
 }
 
 struct Job<'a> {
     pub rng: RandomNumberGenerator,
     pub row: usize,
     pub col: usize,
-    pub world: &'a HittableList,
+    pub integrator: &'a dyn Integrator,
     pub camera: Camera,
     pub image_width: i32,
     pub image_height: i32,
     pub samples_per_pixel_x: i32,
     pub samples_per_pixel_y: i32,
-    pub max_depth: i32,
 }
 
-unsafe impl Send for Job<'_> {}
-
 fn render_job(job: &mut Job) -> Color {
     let mut color = Color {
         x: 0.0,
@@ -308,6 +517,7 @@ fn render_job(job: &mut Job) -> Color {
 
     let width_minus_one = job.image_width as f64 - 1.0;
     let height_minus_one = job.image_height as f64 - 1.0;
+    let pixel_index = (job.row * job.image_width as usize + job.col) as u64;
 
     for sample_y in 0..job.samples_per_pixel_y {
         let y = (sample_y as f64) / (job.samples_per_pixel_y as f64) - 0.5;
@@ -319,136 +529,262 @@ fn render_job(job: &mut Job) -> Color {
             //     job.col, job.row, sample_x, sample_y
             // );
 
+            let sample_index = (sample_y * job.samples_per_pixel_x + sample_x) as u64;
+            let mut rng = job.rng.for_sample(pixel_index, sample_index);
+
             let x = (sample_x as f64) / (job.samples_per_pixel_x as f64) - 0.5;
             let u = (job.col as f64 + x) / width_minus_one;
-            let r = job.camera.get_ray(&mut job.rng, u, v);
-            color.add_assign(ray_color(
-                job.row,
-                job.col,
-                r,
-                &job.world,
-                job.max_depth,
-                &mut job.rng,
-            ));
+            let r = job.camera.get_ray(&mut rng, u, v);
+            color.add_assign(job.integrator.ray_color(&mut rng, r));
         }
     }
 
     return color;
 }
 
-fn render(image_width: i32, image_height: i32, jobs: &mut [Job], pixels: &mut Vec<Vec<Color>>) {
+fn render(image_width: i32, image_height: i32, jobs: &mut [Job], pixels: &mut [Vec<Color>]) {
     let job_count = image_width * image_height;
-    jobs.iter_mut()
-        .zip(0..job_count)
-        .for_each(|(job, job_index): (&mut Job, i32)| {
-            if job_index % image_width == 0 {
-                let remaining_lines = (job_count - job_index) / image_width;
+    let finished_jobs = AtomicI32::new(0);
+    let colors: Vec<Color> = jobs
+        .par_iter_mut()
+        .map(|job: &mut Job| {
+            let color = render_job(job);
+            let finished = finished_jobs.fetch_add(1, Ordering::Relaxed) + 1;
+            // The last line is reported once all the pixels are stored.
+            if finished % image_width == 0 && finished < job_count {
+                let remaining_lines = (job_count - finished) / image_width;
                 eprint!("Lines remaining: {}  \r", remaining_lines);
             }
-            pixels[job.row][job.col] = render_job(job);
-        });
+            color
+        })
+        .collect();
 
-    eprint!("Lines remaining: 0  \n");
+    for (job, color) in jobs.iter().zip(colors) {
+        pixels[job.row][job.col] = color;
+    }
+
+    eprintln!("Lines remaining: 0  ");
 }
 
 fn main() {
-    const ASPECT_RATIO: f64 = 16.0 / 10.0;
-    const IMAGE_WIDTH: i32 = 1920;
-    const IMAGE_HEIGHT: i32 = ((IMAGE_WIDTH as f64) / ASPECT_RATIO) as i32;
-    const SAMPLES_PER_PIXEL_X: i32 = 16;
-    const SAMPLES_PER_PIXEL_Y: i32 = 16;
-    const SAMPLE_COUNT: i32 = SAMPLES_PER_PIXEL_X * SAMPLES_PER_PIXEL_Y;
-    const JOB_COUNT: i32 = IMAGE_HEIGHT * IMAGE_WIDTH;
-    const MAX_DEPTH: i32 = 50;
+    let args: Vec<String> = env::args().collect();
+    let options = match Options::parse(&args[1..]) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!(
+                "error: {}\n\nRun with --help to see the available options.",
+                message
+            );
+            process::exit(2);
+        }
+    };
+
+    if options.help {
+        print!("{}", USAGE);
+        return;
+    }
+
+    if options.threads > 0 {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(options.threads)
+            .build_global()
+            .expect("failed to configure the thread pool");
+    }
+
+    let mut scene = match options.scene.as_str() {
+        "simple" => {
+            eprintln!("Loading simple scene");
+            Scene {
+                world: simple_scene(),
+                lights: HittableList { objects: vec![] },
+                delta_lights: vec![],
+                camera: CameraSettings::default(),
+                settings: RenderSettings::default(),
+                background: Background::Gradient,
+            }
+        }
+        "random" | "random-stone" => {
+            eprintln!("Loading {} scene", options.scene);
+            let seed = options.seed.unwrap_or(RenderSettings::default().seed);
+            let mut rng = RandomNumberGenerator::create(seed, options.rng_algorithm);
+            // The noise has a stream of its own so that it neither shifts nor
+            // repeats the random numbers of the sphere layout.
+            let noise = if options.scene == "random-stone" {
+                let mut noise_rng = rng.for_stream(NOISE_STREAM);
+                Some(Arc::new(Perlin::create(&mut noise_rng)))
+            } else {
+                None
+            };
+            Scene {
+                world: random_scene(&mut rng, noise),
+                lights: HittableList { objects: vec![] },
+                delta_lights: vec![],
+                camera: CameraSettings::default(),
+                settings: RenderSettings::default(),
+                background: Background::Gradient,
+            }
+        }
+        "cornell" => {
+            eprintln!("Loading Cornell box scene");
+            cornell_box_scene()
+        }
+        path => {
+            eprintln!("Loading scene from {}", path);
+            match load_scene(path) {
+                Ok(scene) => scene,
+                Err(message) => {
+                    eprintln!("error: {}", message);
+                    process::exit(2);
+                }
+            }
+        }
+    };
+
+    options.apply(&mut scene);
+    if let Err(message) = scene.settings.validate().and(scene.camera.validate()) {
+        eprintln!("error: {}", message);
+        process::exit(2);
+    }
+
+    let settings = scene.settings;
+    let image_width = settings.image_width;
+    let image_height = settings.image_height;
+    let sample_count = settings.samples_per_pixel_x * settings.samples_per_pixel_y;
+    let job_count = image_height * image_width;
 
     eprintln!(
         "Rendering {}x{} image with {}x{} samples per pixel",
-        IMAGE_WIDTH, IMAGE_HEIGHT, SAMPLES_PER_PIXEL_X, SAMPLES_PER_PIXEL_Y
+        image_width, image_height, settings.samples_per_pixel_x, settings.samples_per_pixel_y
     );
 
-    let mut rng = RandomNumberGenerator::create();
-    rng.random_double();
-
-    let args: Vec<String> = env::args().collect();
-
-    let world = if args.iter().any(|i| i == "simple") {
-        eprintln!("Loading simple scene");
-        simple_scene()
-    } else {
-        eprintln!("Loading random scene");
-        random_scene(&mut rng)
+    let rng = RandomNumberGenerator::create(settings.seed, options.rng_algorithm);
+
+    eprintln!("Building BVH for {} objects", scene.world.objects.len());
+    let world = build_bvh(scene.world);
+
+    let integrator: Box<dyn Integrator> = match settings.integrator {
+        IntegratorKind::Path => Box::new(PathIntegrator {
+            world: world.as_ref(),
+            lights: &scene.lights,
+            delta_lights: &scene.delta_lights,
+            background: scene.background.clone(),
+            max_depth: settings.max_depth,
+            roulette_depth: settings.roulette_depth,
+        }),
+        IntegratorKind::Simple => Box::new(SimpleIntegrator {
+            world: world.as_ref(),
+            background: scene.background.clone(),
+            max_depth: settings.max_depth,
+        }),
     };
 
-    const LOOKFROM: Point3 = Point3 {
-        x: 13.0,
-        y: 2.0,
-        z: 3.0,
-    };
-    const LOOKAT: Point3 = Point3 {
-        x: 0.0,
-        y: 0.0,
-        z: 0.0,
-    };
-    const VUP: Vec3 = Vec3 {
-        x: 0.0,
-        y: 1.0,
-        z: 0.0,
-    };
-    const VFOV: f64 = 20.0; // vertical field-of-view in degrees
-    const FOCUS_DIST: f64 = 10.0;
-    const APERTURE: f64 = 0.1;
-
-    let camera: Camera = Camera::create(
-        LOOKFROM,
-        LOOKAT,
-        VUP,
-        VFOV,
-        ASPECT_RATIO,
-        APERTURE,
-        FOCUS_DIST,
-    );
+    let camera: Camera = scene.camera.create_camera(settings.aspect_ratio());
 
-    let mut pixels: Vec<Vec<Color>> = (0..IMAGE_HEIGHT)
+    let mut pixels: Vec<Vec<Color>> = (0..image_height)
         .map(|_| {
-            (0..IMAGE_WIDTH)
+            (0..image_width)
                 .map(|_| Color::default())
                 .collect::<Vec<_>>()
         })
         .collect::<Vec<_>>();
-    let mut jobs: Vec<Job> = Vec::with_capacity(JOB_COUNT as usize);
+    let mut jobs: Vec<Job> = Vec::with_capacity(job_count as usize);
 
-    for row in 0usize..(IMAGE_HEIGHT as usize) {
+    for row in 0usize..(image_height as usize) {
         // eprint!("Creating jobs for line: {}  \r", row);
-        for col in 0usize..(IMAGE_WIDTH as usize) {
+        for col in 0usize..(image_width as usize) {
             let job = Job {
                 rng: rng.clone(),
                 row,
                 col,
-                world: &world,
+                integrator: integrator.as_ref(),
                 camera,
-                image_width: IMAGE_WIDTH,
-                image_height: IMAGE_HEIGHT,
-                samples_per_pixel_x: SAMPLES_PER_PIXEL_X,
-                samples_per_pixel_y: SAMPLES_PER_PIXEL_Y,
-                max_depth: MAX_DEPTH,
+                image_width,
+                image_height,
+                samples_per_pixel_x: settings.samples_per_pixel_x,
+                samples_per_pixel_y: settings.samples_per_pixel_y,
             };
             jobs.push(job);
         }
     }
 
     // eprint!("\n");
-    eprintln!("Created {} jobs", JOB_COUNT);
-
-    render(IMAGE_WIDTH, IMAGE_HEIGHT, jobs.as_mut_slice(), &mut pixels);
+    eprintln!("Created {} jobs", job_count);
+
+    render(image_width, image_height, jobs.as_mut_slice(), &mut pixels);
+
+    eprintln!("Jobs finished");
+    eprintln!("Writing image");
+
+    if let Err(error) = write_image(
+        &options.output,
+        options.format,
+        options.bit_depth,
+        &pixels,
+        sample_count,
+    ) {
+        eprintln!("error: cannot write {}: {}", options.output, error);
+        process::exit(1);
+    }
+    eprintln!("\nDone");
+}
 
-    eprint!("Jobs finished\n");
-    eprint!("Writing image\n");
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Renders a small Cornell box with the path integrator on a pool of
+    // `threads` threads.
+    fn render_cornell_box(threads: usize) -> Vec<Vec<Color>> {
+        let scene = cornell_box_scene();
+        let world = build_bvh(scene.world);
+        let integrator = PathIntegrator {
+            world: world.as_ref(),
+            lights: &scene.lights,
+            delta_lights: &scene.delta_lights,
+            background: scene.background.clone(),
+            max_depth: 8,
+            roulette_depth: Some(2),
+        };
+        let camera = scene.camera.create_camera(1.0);
+        let rng = RandomNumberGenerator::create(675248, RngAlgorithm::Pcg32);
+
+        let size = 12;
+        let mut jobs: Vec<Job> = vec![];
+        for row in 0..size as usize {
+            for col in 0..size as usize {
+                jobs.push(Job {
+                    rng: rng.clone(),
+                    row,
+                    col,
+                    integrator: &integrator,
+                    camera,
+                    image_width: size,
+                    image_height: size,
+                    samples_per_pixel_x: 2,
+                    samples_per_pixel_y: 2,
+                });
+            }
+        }
+        let mut pixels = vec![vec![Color::default(); size as usize]; size as usize];
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap()
+            .install(|| render(size, size, &mut jobs, &mut pixels));
+        return pixels;
+    }
 
-    println!("P3\n{} {}\n255", IMAGE_WIDTH, IMAGE_HEIGHT);
-    for row in (0usize..(IMAGE_HEIGHT as usize)).rev() {
-        for col in 0usize..(IMAGE_WIDTH as usize) {
-            write_color(pixels[row][col], SAMPLE_COUNT);
+    #[test]
+    fn same_seed_renders_the_same_pixels_on_any_number_of_threads() {
+        let one_thread = render_cornell_box(1);
+        let four_threads = render_cornell_box(4);
+        assert!(one_thread.iter().flatten().any(|color| color.x > 0.0));
+        for (a, b) in one_thread
+            .iter()
+            .flatten()
+            .zip(four_threads.iter().flatten())
+        {
+            assert_eq!((a.x, a.y, a.z), (b.x, b.y, b.z));
         }
     }
-    eprintln!("\nDone");
 }
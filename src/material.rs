@@ -3,8 +3,8 @@ use super::ray::*;
 use super::rng::*;
 use super::vec3::*;
 
-pub trait Material {
-    fn diffuse(&self) -> Color;
+pub trait Material: Send + Sync {
+    fn diffuse(&self, rec: &HitRecord) -> Color;
     fn scatter(
         &self,
         rng: &mut RandomNumberGenerator,
@@ -13,4 +13,40 @@ pub trait Material {
         attenuation: &mut Color,
         scattered: &mut Ray
     ) -> bool;
+
+    // Light given off by the surface towards the incoming ray, black unless
+    // the material is a light source.
+    fn emitted(&self, _r_in: Ray, _rec: &HitRecord) -> Color {
+        return Color {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        };
+    }
+
+    // Whether the surface is a light source worth sampling directly.
+    fn is_emissive(&self) -> bool {
+        return false;
+    }
+
+    // Specular materials scatter into directions a light sample would never
+    // pick, so lights are only sampled directly at the other ones.
+    fn is_specular(&self) -> bool {
+        return true;
+    }
+
+    // The fraction of light arriving from `direction` that is scattered
+    // along the incoming ray, cosine term included.
+    fn eval(&self, _r_in: Ray, _rec: &HitRecord, _direction: Vec3) -> Color {
+        return Color {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        };
+    }
+
+    // The density, per solid angle, with which `scatter` picks `direction`.
+    fn pdf(&self, _r_in: Ray, _rec: &HitRecord, _direction: Vec3) -> f64 {
+        return 0.0;
+    }
 }
@@ -0,0 +1,150 @@
+use super::aabb::*;
+use super::hit_record::*;
+use super::hittable::*;
+use super::hittable_list::*;
+use super::material::*;
+use super::ray::*;
+use super::rng::*;
+use super::vec3::*;
+use std::sync::Arc;
+
+// Vertex buffers shared by all the triangles of a mesh.
+#[derive(Default)]
+pub struct Mesh {
+    pub positions: Vec<Point3>,
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<(f64, f64)>,
+}
+
+// A corner of a triangle as indices into the mesh buffers. Normals and
+// texture coordinates are optional.
+#[derive(Copy, Clone)]
+pub struct MeshVertex {
+    pub position: usize,
+    pub normal: Option<usize>,
+    pub uv: Option<usize>,
+}
+
+pub struct Triangle {
+    mesh: Arc<Mesh>,
+    vertices: [MeshVertex; 3],
+    material: Arc<dyn Material>,
+}
+
+impl Triangle {
+    pub fn create(
+        mesh: Arc<Mesh>,
+        vertices: [MeshVertex; 3],
+        material: Arc<dyn Material>,
+    ) -> Triangle {
+        return Triangle {
+            mesh,
+            vertices,
+            material,
+        };
+    }
+
+    fn position(&self, corner: usize) -> Point3 {
+        return self.mesh.positions[self.vertices[corner].position];
+    }
+}
+
+// Adds one triangle per face, so that each of them ends up in the BVH on its
+// own rather than the mesh being a single leaf.
+pub fn add_mesh(
+    list: &mut HittableList,
+    mesh: Arc<Mesh>,
+    faces: &[[MeshVertex; 3]],
+    material: Arc<dyn Material>,
+) {
+    for face in faces {
+        list.add(Arc::new(Triangle::create(
+            mesh.clone(),
+            *face,
+            material.clone(),
+        )));
+    }
+}
+
+impl Hittable for Triangle {
+    // Möller–Trumbore ray-triangle intersection.
+    fn hit(
+        &self,
+        _: &mut RandomNumberGenerator,
+        r: Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+    ) -> bool {
+        let p0 = self.position(0);
+        let edge1 = self.position(1) - p0;
+        let edge2 = self.position(2) - p0;
+
+        let pvec = cross(r.direction, edge2);
+        let det = dot(edge1, pvec);
+        if det.abs() < 1e-12 {
+            return false;
+        }
+        let inv_det = 1.0 / det;
+
+        let tvec = r.origin - p0;
+        let b1 = dot(tvec, pvec) * inv_det;
+        if !(0.0..=1.0).contains(&b1) {
+            return false;
+        }
+
+        let qvec = cross(tvec, edge1);
+        let b2 = dot(r.direction, qvec) * inv_det;
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return false;
+        }
+
+        let t = dot(edge2, qvec) * inv_det;
+        if t < t_min || t_max < t {
+            return false;
+        }
+        let b0 = 1.0 - b1 - b2;
+
+        // The geometric normal decides which side was hit, the interpolated
+        // vertex normals, when there are any, only shade.
+        let geometric_normal = unit_vector(cross(edge1, edge2));
+        let mut normal = geometric_normal;
+        if let [Some(n0), Some(n1), Some(n2)] = self.vertices.map(|vertex| vertex.normal) {
+            let normals = &self.mesh.normals;
+            let interpolated = normals[n0] * b0 + normals[n1] * b1 + normals[n2] * b2;
+            if !near_zero(interpolated) {
+                normal = unit_vector(interpolated);
+                if dot(normal, geometric_normal) < 0.0 {
+                    normal = -normal;
+                }
+            }
+        }
+
+        rec.t = t;
+        rec.p = r.at(t);
+        rec.front_face = dot(r.direction, geometric_normal) < 0.0;
+        rec.normal = if rec.front_face { normal } else { -normal };
+        (rec.u, rec.v) = match self.vertices.map(|vertex| vertex.uv) {
+            [Some(t0), Some(t1), Some(t2)] => {
+                let uvs = &self.mesh.uvs;
+                (
+                    uvs[t0].0 * b0 + uvs[t1].0 * b1 + uvs[t2].0 * b2,
+                    uvs[t0].1 * b0 + uvs[t1].1 * b1 + uvs[t2].1 * b2,
+                )
+            }
+            _ => (b1, b2),
+        };
+        rec.material = self.material.clone();
+
+        return true;
+    }
+
+    fn bounding_box(&self, output_box: &mut Aabb) -> bool {
+        let mut bbox = Aabb::default();
+        for corner in 0..3 {
+            bbox = surrounding_point(bbox, self.position(corner));
+        }
+        *output_box = bbox.pad();
+        return true;
+    }
+}
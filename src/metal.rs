@@ -2,17 +2,19 @@ use super::hit_record::*;
 use super::material::*;
 use super::ray::*;
 use super::rng::*;
+use super::texture::*;
 use super::vec3::*;
+use std::f64::consts::PI;
+use std::sync::Arc;
 
-#[derive(Copy, Clone)]
 pub struct Metal {
-    pub albedo: Color,
+    pub albedo: Arc<dyn Texture>,
     pub fuzz: f64,
 }
 
 impl Material for Metal {
-    fn diffuse(&self) -> Color {
-        return self.albedo;
+    fn diffuse(&self, rec: &HitRecord) -> Color {
+        return self.albedo.value(rec.u, rec.v, rec.p);
     }
 
     fn scatter(
@@ -27,8 +29,99 @@ impl Material for Metal {
         *scattered = Ray {
             origin: rec.p,
             direction: reflected + rng.random_in_unit_sphere() * self.fuzz,
+            time: r_in.time,
         };
-        *attenuation = self.albedo;
+        *attenuation = self.albedo.value(rec.u, rec.v, rec.p);
         return dot(scattered.direction, rec.normal) > 0.0;
     }
+
+    // Only a perfect mirror is specular, a fuzzy reflection can be sampled
+    // towards the lights.
+    fn is_specular(&self) -> bool {
+        return self.fuzz <= 0.0;
+    }
+
+    // Directions below the surface are absorbed, the others are weighted by
+    // the albedo just as the scattered rays are.
+    fn eval(&self, r_in: Ray, rec: &HitRecord, direction: Vec3) -> Color {
+        if dot(direction, rec.normal) <= 0.0 {
+            return Color {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+            };
+        }
+        return self.albedo.value(rec.u, rec.v, rec.p) * self.pdf(r_in, rec, direction);
+    }
+
+    // The scattered direction is the mirror direction plus a uniform point
+    // in a ball of radius `fuzz`. Its density per solid angle is the volume
+    // of the ball along the ray in `direction`, between the distances t0 and
+    // t1 where the ray enters and leaves it, over the volume of the ball.
+    fn pdf(&self, r_in: Ray, rec: &HitRecord, direction: Vec3) -> f64 {
+        if self.fuzz <= 0.0 {
+            return 0.0;
+        }
+        let reflected = reflect(unit_vector(r_in.direction), rec.normal);
+        let half_b = dot(unit_vector(direction), reflected);
+        let c = reflected.length_squared() - self.fuzz * self.fuzz;
+        let discriminant = half_b * half_b - c;
+        if discriminant <= 0.0 {
+            return 0.0;
+        }
+        let t1 = half_b + discriminant.sqrt();
+        if t1 <= 0.0 {
+            return 0.0;
+        }
+        let t0 = (half_b - discriminant.sqrt()).max(0.0);
+        return (t1.powi(3) - t0.powi(3)) / (4.0 * PI * self.fuzz.powi(3));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The fuzz-ball density is a density over the whole sphere of
+    // directions, the ones below the surface included, so a Monte Carlo
+    // estimate of its integral from uniform directions comes out at 1.
+    #[test]
+    fn the_fuzz_ball_pdf_integrates_to_one() {
+        let r_in = Ray {
+            origin: Point3 {
+                x: -1.0,
+                y: 1.0,
+                z: 0.0,
+            },
+            direction: Vec3 {
+                x: 1.0,
+                y: -1.0,
+                z: 0.0,
+            },
+            time: 0.0,
+        };
+        let mut rec = HitRecord::new();
+        rec.normal = Vec3 {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        };
+        let mut rng = RandomNumberGenerator::create(4177, RngAlgorithm::Pcg32);
+        for fuzz in [0.3, 1.0, 1.5] {
+            let metal = Metal {
+                albedo: Arc::new(SolidColor {
+                    color: Color::default(),
+                }),
+                fuzz,
+            };
+            let samples = 200000;
+            let mut sum = 0.0;
+            for _ in 0..samples {
+                let direction = rng.random_unit_vector();
+                sum += metal.pdf(r_in, &rec, direction);
+            }
+            let integral = 4.0 * PI * sum / samples as f64;
+            assert!((integral - 1.0).abs() < 0.03, "fuzz {}: {}", fuzz, integral);
+        }
+    }
 }
@@ -0,0 +1,175 @@
+use super::onb::*;
+use super::rng::*;
+use super::vec3::*;
+use std::f64::consts::PI;
+
+// Below this `alpha` a surface is treated as perfectly smooth.
+pub const MIN_ALPHA: f64 = 1e-3;
+
+// The GGX (Trowbridge-Reitz) distribution of microfacet normals with Smith
+// masking and shadowing. Angles are given by their cosines to the
+// macroscopic normal. `alpha` is the square of the perceptual roughness, so
+// that roughness looks about linear from 0 for a mirror to 1.
+#[derive(Copy, Clone)]
+pub struct Ggx {
+    pub alpha: f64,
+}
+
+impl Ggx {
+    pub fn from_roughness(roughness: f64) -> Ggx {
+        return Ggx {
+            alpha: roughness * roughness,
+        };
+    }
+
+    pub fn is_smooth(&self) -> bool {
+        return self.alpha < MIN_ALPHA;
+    }
+
+    // The density of microfacets with normals at the angle to the normal
+    // whose cosine is `cos_h`, per unit of projected area.
+    pub fn d(&self, cos_h: f64) -> f64 {
+        if cos_h <= 0.0 {
+            return 0.0;
+        }
+        let alpha_squared = self.alpha * self.alpha;
+        let denominator = cos_h * cos_h * (alpha_squared - 1.0) + 1.0;
+        return alpha_squared / (PI * denominator * denominator);
+    }
+
+    // Smith's auxiliary function for a direction at the angle whose cosine
+    // is `cos_theta`.
+    fn lambda(&self, cos_theta: f64) -> f64 {
+        let cos_squared = cos_theta * cos_theta;
+        let tan_squared = (1.0 - cos_squared).max(0.0) / cos_squared;
+        return ((1.0 + self.alpha * self.alpha * tan_squared).sqrt() - 1.0) / 2.0;
+    }
+
+    // The fraction of the microfacets facing a direction that it sees.
+    pub fn g1(&self, cos_theta: f64) -> f64 {
+        return 1.0 / (1.0 + self.lambda(cos_theta));
+    }
+
+    // The fraction seen from both directions at once, with masking and
+    // shadowing correlated by the height of the microfacets.
+    pub fn g2(&self, cos_o: f64, cos_i: f64) -> f64 {
+        return 1.0 / (1.0 + self.lambda(cos_o) + self.lambda(cos_i));
+    }
+
+    // The density of the normals that `sample_visible_normal` picks.
+    pub fn visible_normal_pdf(&self, cos_o: f64, cos_h: f64, o_dot_h: f64) -> f64 {
+        if cos_o <= 0.0 || o_dot_h <= 0.0 {
+            return 0.0;
+        }
+        return self.g1(cos_o) * o_dot_h * self.d(cos_h) / cos_o;
+    }
+
+    // A microfacet normal picked in proportion to how much of it can be
+    // seen from `wo`, which must be above the surface with normal `n`
+    // (Heitz, "Sampling the GGX Distribution of Visible Normals", 2018).
+    pub fn sample_visible_normal(
+        &self,
+        rng: &mut RandomNumberGenerator,
+        n: Vec3,
+        wo: Vec3,
+    ) -> Vec3 {
+        let uvw = Onb::build_from_w(n);
+        let local = uvw.to_local(wo);
+
+        // Stretch the view so that the microfacets become a hemisphere.
+        let vh = unit_vector(Vec3 {
+            x: self.alpha * local.x,
+            y: self.alpha * local.y,
+            z: local.z,
+        });
+        let length_squared = vh.x * vh.x + vh.y * vh.y;
+        let t1 = if length_squared > 0.0 {
+            Vec3 {
+                x: -vh.y,
+                y: vh.x,
+                z: 0.0,
+            } / length_squared.sqrt()
+        } else {
+            Vec3 {
+                x: 1.0,
+                y: 0.0,
+                z: 0.0,
+            }
+        };
+        let t2 = cross(vh, t1);
+
+        // A point on the projected hemisphere, then back to the microfacet.
+        let r = rng.random_double().sqrt();
+        let phi = 2.0 * PI * rng.random_double();
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + vh.z);
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+        let nh = t1 * p1 + t2 * p2 + vh * (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt();
+
+        return uvw.local(unit_vector(Vec3 {
+            x: self.alpha * nh.x,
+            y: self.alpha * nh.y,
+            z: nh.z.max(0.0),
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The bin of a microfacet normal about the z axis: eight bands of its
+    // cosine, split by the side of the plane of incidence it leans to.
+    fn bin(h: Vec3) -> usize {
+        let band = ((h.z * 8.0) as usize).min(7);
+        return 2 * band + (h.x > 0.0) as usize;
+    }
+
+    // The fraction of the sampled normals falling in each bin matches the
+    // integral of `visible_normal_pdf` over that bin, estimated from
+    // directions spread uniformly over the hemisphere.
+    #[test]
+    fn visible_normals_are_sampled_with_their_pdf() {
+        let n = Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: 1.0,
+        };
+        let mut rng = RandomNumberGenerator::create(2718, RngAlgorithm::Pcg32);
+        for (alpha, cos_o) in [(0.25, 0.9), (0.5, 0.5), (0.8, 0.2)] {
+            let distribution = Ggx { alpha };
+            let wo = Vec3 {
+                x: f64::sqrt(1.0 - cos_o * cos_o),
+                y: 0.0,
+                z: cos_o,
+            };
+            let samples = 200000;
+
+            let mut sampled = [0.0; 16];
+            for _ in 0..samples {
+                let h = distribution.sample_visible_normal(&mut rng, n, wo);
+                sampled[bin(h)] += 1.0 / samples as f64;
+            }
+
+            let mut integrated = [0.0; 16];
+            for _ in 0..samples {
+                let mut h = rng.random_unit_vector();
+                h.z = h.z.abs();
+                let pdf = distribution.visible_normal_pdf(cos_o, h.z, dot(wo, h));
+                integrated[bin(h)] += 2.0 * PI * pdf / samples as f64;
+            }
+
+            for i in 0..16 {
+                assert!(
+                    (sampled[i] - integrated[i]).abs() < 0.01,
+                    "alpha {} cos_o {} bin {}: sampled {} integrated {}",
+                    alpha,
+                    cos_o,
+                    i,
+                    sampled[i],
+                    integrated[i]
+                );
+            }
+        }
+    }
+}
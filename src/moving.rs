@@ -0,0 +1,248 @@
+use super::aabb::*;
+use super::hit_record::*;
+use super::hittable::*;
+use super::ray::*;
+use super::rng::*;
+use super::vec3::*;
+use std::sync::Arc;
+
+// Where a moving object has got to by the end of its motion: scaled by
+// `scale` and rotated by `rotation` about `pivot`, then moved by
+// `displacement`.
+#[derive(Copy, Clone)]
+pub struct Motion {
+    pub displacement: Vec3,
+    pub rotation: Quaternion,
+    pub scale: Vec3,
+    pub pivot: Point3,
+}
+
+// Moves any object from where it was defined at time0 to the end of its
+// motion at time1. The displacement and the scale change at a constant rate
+// and the rotation turns at a constant rate the short way round, so a
+// rotation should stay below 180 degrees. Before time0 the object is where
+// it was defined, after time1 it rests at the end of the motion.
+pub struct Moving {
+    pub object: Arc<dyn Hittable>,
+    pub motion: Motion,
+    pub time0: f64,
+    pub time1: f64,
+}
+
+impl Moving {
+    // How far along its motion the object is at `time`, from 0 to 1.
+    fn progress(&self, time: f64) -> f64 {
+        if self.time1 <= self.time0 {
+            return if time < self.time0 { 0.0 } else { 1.0 };
+        }
+        return ((time - self.time0) / (self.time1 - self.time0)).clamp(0.0, 1.0);
+    }
+
+    // The transform that places the object `s` of the way along its motion,
+    // and its inverse.
+    fn transforms(&self, s: f64) -> (Mat4, Mat4) {
+        let motion = &self.motion;
+        let one = Vec3 {
+            x: 1.0,
+            y: 1.0,
+            z: 1.0,
+        };
+        let scale = one * (1.0 - s) + motion.scale * s;
+        let rotation = Quaternion::identity().slerp(motion.rotation, s).to_mat4();
+        let offset = motion.pivot + motion.displacement * s;
+
+        let transform = Mat4::translation(offset)
+            * rotation
+            * Mat4::scaling(scale)
+            * Mat4::translation(-motion.pivot);
+        let inverse = Mat4::translation(motion.pivot)
+            * Mat4::scaling(Vec3 {
+                x: 1.0 / scale.x,
+                y: 1.0 / scale.y,
+                z: 1.0 / scale.z,
+            })
+            * rotation.transpose()
+            * Mat4::translation(-offset);
+        return (transform, inverse);
+    }
+}
+
+impl Hittable for Moving {
+    fn hit(
+        &self,
+        rng: &mut RandomNumberGenerator,
+        r: Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+    ) -> bool {
+        // Move the ray back instead of the object forward, as `Instance`
+        // does with its transform.
+        let (transform, inverse) = self.transforms(self.progress(r.time));
+        let moved = Ray {
+            origin: inverse.transform_point(r.origin),
+            direction: inverse.transform_vector(r.direction),
+            time: r.time,
+        };
+        if !self.object.hit(rng, moved, t_min, t_max, rec) {
+            return false;
+        }
+        rec.p = transform.transform_point(rec.p);
+        rec.normal = unit_vector(inverse.transpose().transform_vector(rec.normal));
+        return true;
+    }
+
+    fn bounding_box(&self, output_box: &mut Aabb) -> bool {
+        let mut object_box = Aabb::default();
+        if !self.object.bounding_box(&mut object_box) {
+            return false;
+        }
+
+        // Without a rotation every coordinate of a corner changes linearly
+        // with time, so the boxes at both ends cover the whole motion.
+        let mut bbox = Aabb::default();
+        for s in [0.0, 1.0] {
+            let (transform, _) = self.transforms(s);
+            for corner in 0..8 {
+                let p = Point3 {
+                    x: if corner & 1 == 0 {
+                        object_box.minimum.x
+                    } else {
+                        object_box.maximum.x
+                    },
+                    y: if corner & 2 == 0 {
+                        object_box.minimum.y
+                    } else {
+                        object_box.maximum.y
+                    },
+                    z: if corner & 4 == 0 {
+                        object_box.minimum.z
+                    } else {
+                        object_box.maximum.z
+                    },
+                };
+                bbox = surrounding_point(bbox, transform.transform_point(p));
+            }
+        }
+
+        // A rotating object stays within the distance from the pivot its
+        // farthest point can reach at the largest scale, around the pivot
+        // as it moves.
+        let rotation = self.motion.rotation;
+        if rotation.w.abs() < 1.0 {
+            let center = (object_box.minimum + object_box.maximum) * 0.5;
+            let half_diagonal = (object_box.maximum - object_box.minimum).length() / 2.0;
+            let scale = &self.motion.scale;
+            let largest_scale = scale.x.abs().max(scale.y.abs()).max(scale.z.abs()).max(1.0);
+            let reach = largest_scale * ((center - self.motion.pivot).length() + half_diagonal);
+            let radius = Vec3 {
+                x: reach,
+                y: reach,
+                z: reach,
+            };
+            for end in [
+                self.motion.pivot,
+                self.motion.pivot + self.motion.displacement,
+            ] {
+                bbox = surrounding_box(
+                    bbox,
+                    Aabb {
+                        minimum: end - radius,
+                        maximum: end + radius,
+                    },
+                );
+            }
+        }
+        *output_box = bbox;
+        return true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::cuboid::*;
+    use super::super::diffuse_light::*;
+    use super::*;
+
+    #[test]
+    fn the_bounding_box_covers_the_whole_motion() {
+        let minimum = Point3 {
+            x: 1.0,
+            y: 0.0,
+            z: -0.5,
+        };
+        let maximum = Point3 {
+            x: 3.0,
+            y: 0.5,
+            z: 0.5,
+        };
+        let light = Arc::new(DiffuseLight {
+            emit: Color::default(),
+        });
+        // A quarter turn swings the far end of the box outside the boxes at
+        // both ends of the motion.
+        let moving = Moving {
+            object: Arc::new(Cuboid::create(minimum, maximum, light)),
+            motion: Motion {
+                displacement: Vec3 {
+                    x: 0.0,
+                    y: 1.0,
+                    z: 0.0,
+                },
+                rotation: Quaternion::rotation(
+                    Vec3 {
+                        x: 0.0,
+                        y: 1.0,
+                        z: 0.0,
+                    },
+                    90.0,
+                ),
+                scale: Vec3 {
+                    x: 1.5,
+                    y: 1.0,
+                    z: 1.0,
+                },
+                pivot: Point3 {
+                    x: 0.0,
+                    y: 0.0,
+                    z: 0.0,
+                },
+            },
+            time0: 0.0,
+            time1: 1.0,
+        };
+        let mut bbox = Aabb::default();
+        assert!(moving.bounding_box(&mut bbox));
+
+        for time in [0.0, 0.25, 0.5, 0.75, 1.0] {
+            let (transform, _) = moving.transforms(moving.progress(time));
+            for corner in 0..8 {
+                let p = transform.transform_point(Point3 {
+                    x: if corner & 1 == 0 {
+                        minimum.x
+                    } else {
+                        maximum.x
+                    },
+                    y: if corner & 2 == 0 {
+                        minimum.y
+                    } else {
+                        maximum.y
+                    },
+                    z: if corner & 4 == 0 {
+                        minimum.z
+                    } else {
+                        maximum.z
+                    },
+                });
+                for axis in 0..3 {
+                    assert!(
+                        bbox.minimum[axis] <= p[axis] && p[axis] <= bbox.maximum[axis],
+                        "time {}: {} is outside the box",
+                        time,
+                        p
+                    );
+                }
+            }
+        }
+    }
+}
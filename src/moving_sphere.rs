@@ -0,0 +1,90 @@
+use super::aabb::*;
+use super::hit_record::*;
+use super::hittable::*;
+use super::material::*;
+use super::ray::*;
+use super::rng::*;
+use super::sphere::*;
+use super::vec3::*;
+use std::sync::Arc;
+
+// A sphere moving in a straight line from center0 at time0 to center1 at
+// time1. It rests at either end outside of that interval, so its bounding box
+// holds whatever the camera shutter is.
+pub struct MovingSphere {
+    pub center0: Point3,
+    pub center1: Point3,
+    pub time0: f64,
+    pub time1: f64,
+    pub radius: f64,
+    pub material: Arc<dyn Material>,
+}
+
+impl MovingSphere {
+    pub fn center(&self, time: f64) -> Point3 {
+        if self.time1 <= self.time0 {
+            return self.center0;
+        }
+        let s = ((time - self.time0) / (self.time1 - self.time0)).clamp(0.0, 1.0);
+        return self.center0 + (self.center1 - self.center0) * s;
+    }
+}
+
+impl Hittable for MovingSphere {
+    fn hit(
+        &self,
+        _: &mut RandomNumberGenerator,
+        r: Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+    ) -> bool {
+        let center = self.center(r.time);
+        let oc = r.origin - center;
+        let a = r.direction.length_squared();
+        let half_b = dot(oc, r.direction);
+        let c = oc.length_squared() - self.radius * self.radius;
+
+        let discriminant = half_b * half_b - a * c;
+        if discriminant < 0.0 {
+            return false;
+        }
+        let sqrtd = discriminant.sqrt();
+
+        // Find the nearest root that lies in the acceptable range.
+        let mut t = (-half_b - sqrtd) / a;
+        if t < t_min || t_max < t {
+            t = (-half_b + sqrtd) / a;
+            if t < t_min || t_max < t {
+                return false;
+            }
+        }
+
+        rec.t = t;
+        rec.p = r.at(rec.t);
+        let outward_normal = (rec.p - center) / self.radius;
+        rec.set_face_normal(r, outward_normal);
+        (rec.u, rec.v) = sphere_uv(outward_normal);
+        rec.material = self.material.clone();
+
+        return true;
+    }
+
+    fn bounding_box(&self, output_box: &mut Aabb) -> bool {
+        let r = Vec3 {
+            x: self.radius,
+            y: self.radius,
+            z: self.radius,
+        };
+        let box0 = Aabb {
+            minimum: self.center0 - r,
+            maximum: self.center0 + r,
+        };
+        let box1 = Aabb {
+            minimum: self.center1 - r,
+            maximum: self.center1 + r,
+        };
+        *output_box = surrounding_box(box0, box1);
+        return true;
+    }
+}
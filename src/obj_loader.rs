@@ -0,0 +1,226 @@
+use super::mesh::*;
+use super::vec3::*;
+use std::fs;
+use std::sync::Arc;
+
+// The faces that follow a `usemtl` statement, or the faces before the first
+// one when `material` is None.
+pub struct ObjGroup {
+    pub material: Option<String>,
+    pub faces: Vec<[MeshVertex; 3]>,
+}
+
+pub struct ObjModel {
+    pub mesh: Arc<Mesh>,
+    pub groups: Vec<ObjGroup>,
+}
+
+// Reads the geometry of a Wavefront OBJ file: vertex positions, texture
+// coordinates, normals and faces, grouped by `usemtl`. Polygons are split
+// into a fan of triangles. Other statements (objects, groups, smoothing,
+// material libraries, lines and points) are ignored.
+pub fn load_obj(path: &str) -> Result<ObjModel, String> {
+    let text = fs::read_to_string(path).map_err(|error| format!("{}: {}", path, error))?;
+    return parse_obj(path, &text);
+}
+
+// Parses `text`, the contents of the OBJ file at `path`.
+fn parse_obj(path: &str, text: &str) -> Result<ObjModel, String> {
+    let mut mesh = Mesh::default();
+    let mut groups: Vec<ObjGroup> = vec![];
+    let mut material: Option<String> = None;
+
+    for (index, line) in text.lines().enumerate() {
+        let error = |message: String| format!("{}:{}: {}", path, index + 1, message);
+
+        let line = line.split('#').next().unwrap_or("");
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let arguments: Vec<&str> = tokens.collect();
+
+        match keyword {
+            "v" => {
+                let [x, y, z] = parse_numbers::<3>(&arguments, 0).map_err(error)?;
+                mesh.positions.push(Point3 { x, y, z });
+            }
+            "vn" => {
+                let [x, y, z] = parse_numbers::<3>(&arguments, 0).map_err(error)?;
+                mesh.normals.push(Vec3 { x, y, z });
+            }
+            "vt" => {
+                let [u, v] = parse_numbers::<2>(&arguments, 1).map_err(error)?;
+                mesh.uvs.push((u, v));
+            }
+            "f" => {
+                if arguments.len() < 3 {
+                    return Err(error(String::from("a face needs at least three vertices")));
+                }
+                let mut polygon: Vec<MeshVertex> = vec![];
+                for argument in arguments {
+                    polygon.push(parse_vertex(argument, &mesh).map_err(error)?);
+                }
+
+                if groups.last().is_none_or(|group| group.material != material) {
+                    groups.push(ObjGroup {
+                        material: material.clone(),
+                        faces: vec![],
+                    });
+                }
+                let faces = &mut groups.last_mut().unwrap().faces;
+                for i in 1..polygon.len() - 1 {
+                    faces.push([polygon[0], polygon[i], polygon[i + 1]]);
+                }
+            }
+            "usemtl" => {
+                if arguments.is_empty() {
+                    return Err(error(String::from("usemtl needs a material name")));
+                }
+                material = Some(arguments.join(" "));
+            }
+            _ => {}
+        }
+    }
+
+    return Ok(ObjModel {
+        mesh: Arc::new(mesh),
+        groups,
+    });
+}
+
+// Parses N numbers, of which the last `optional` ones may be missing and
+// default to 0. Extra trailing numbers, like the w of a position, are ignored.
+fn parse_numbers<const N: usize>(arguments: &[&str], optional: usize) -> Result<[f64; N], String> {
+    if arguments.len() < N - optional {
+        return Err(format!(
+            "expected {} numbers, got {}",
+            N - optional,
+            arguments.len()
+        ));
+    }
+    let mut numbers = [0.0; N];
+    for (number, argument) in numbers.iter_mut().zip(arguments.iter()) {
+        *number = argument
+            .parse::<f64>()
+            .map_err(|_| format!("expected a number, got '{}'", argument))?;
+    }
+    return Ok(numbers);
+}
+
+// Parses a face vertex written as v, v/vt, v//vn or v/vt/vn. Indices start at
+// 1, negative ones count back from the last element read so far.
+fn parse_vertex(argument: &str, mesh: &Mesh) -> Result<MeshVertex, String> {
+    let mut parts = argument.split('/');
+    let position = parse_index(parts.next(), mesh.positions.len(), "position", argument)?;
+    let uv = parse_index(parts.next(), mesh.uvs.len(), "texture coordinate", argument)?;
+    let normal = parse_index(parts.next(), mesh.normals.len(), "normal", argument)?;
+    if parts.next().is_some() {
+        return Err(format!("malformed face vertex '{}'", argument));
+    }
+
+    return match position {
+        Some(position) => Ok(MeshVertex {
+            position,
+            normal,
+            uv,
+        }),
+        None => Err(format!("face vertex '{}' has no position", argument)),
+    };
+}
+
+fn parse_index(
+    part: Option<&str>,
+    count: usize,
+    what: &str,
+    argument: &str,
+) -> Result<Option<usize>, String> {
+    let part = match part {
+        None | Some("") => return Ok(None),
+        Some(part) => part,
+    };
+    let index = part
+        .parse::<i64>()
+        .map_err(|_| format!("malformed face vertex '{}'", argument))?;
+
+    let resolved = if index < 0 {
+        count as i64 + index
+    } else {
+        index - 1
+    };
+    if index == 0 || resolved < 0 || resolved >= count as i64 {
+        return Err(format!(
+            "{} index {} in face vertex '{}' is out of range, {} defined so far",
+            what, index, argument, count
+        ));
+    }
+    return Ok(Some(resolved as usize));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(faces: &[[MeshVertex; 3]]) -> Vec<[usize; 3]> {
+        return faces
+            .iter()
+            .map(|face| [face[0].position, face[1].position, face[2].position])
+            .collect();
+    }
+
+    const SQUARE: &str = "\
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 1
+vn 0 0 1
+";
+
+    #[test]
+    fn quads_are_split_into_a_fan_of_triangles() {
+        let model = parse_obj("square.obj", &format!("{}f 1 2 3 4\n", SQUARE)).unwrap();
+        assert_eq!(model.groups.len(), 1);
+        assert_eq!(
+            positions(&model.groups[0].faces),
+            vec![[0, 1, 2], [0, 2, 3]]
+        );
+    }
+
+    #[test]
+    fn negative_indices_count_back_from_the_last_element_read() {
+        let text = format!("{}f -4/-2/-1 -3//-1 -2/-1\nv 2 2 0\nf -1 -2 -3\n", SQUARE);
+        let model = parse_obj("square.obj", &text).unwrap();
+        let faces = &model.groups[0].faces;
+        assert_eq!(positions(faces), vec![[0, 1, 2], [4, 3, 2]]);
+        assert_eq!(faces[0][0].uv, Some(0));
+        assert_eq!(faces[0][2].uv, Some(1));
+        assert_eq!(faces[0][1].uv, None);
+        assert_eq!(faces[0][0].normal, Some(0));
+        assert_eq!(faces[0][2].normal, None);
+    }
+
+    #[test]
+    fn usemtl_starts_a_new_group() {
+        let text = format!("{}f 1 2 3\nusemtl red\nf 1 3 4\n", SQUARE);
+        let model = parse_obj("square.obj", &text).unwrap();
+        assert_eq!(model.groups.len(), 2);
+        assert_eq!(model.groups[0].material, None);
+        assert_eq!(model.groups[1].material, Some(String::from("red")));
+    }
+
+    #[test]
+    fn out_of_range_indices_name_the_line() {
+        let error = match parse_obj("square.obj", &format!("{}f 1 2 -5\n", SQUARE)) {
+            Ok(_) => panic!("expected an error"),
+            Err(message) => message,
+        };
+        assert!(
+            error.starts_with("square.obj:8: position index -5"),
+            "{}",
+            error
+        );
+    }
+}
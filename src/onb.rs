@@ -0,0 +1,44 @@
+use super::vec3::*;
+
+// An orthonormal basis around the direction w, for turning directions
+// sampled around the z axis into world space.
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    pub fn build_from_w(n: Vec3) -> Onb {
+        let w = unit_vector(n);
+        let a = if w.x.abs() > 0.9 {
+            Vec3 {
+                x: 0.0,
+                y: 1.0,
+                z: 0.0,
+            }
+        } else {
+            Vec3 {
+                x: 1.0,
+                y: 0.0,
+                z: 0.0,
+            }
+        };
+        let v = unit_vector(cross(w, a));
+        let u = cross(w, v);
+        return Onb { u, v, w };
+    }
+
+    pub fn local(&self, a: Vec3) -> Vec3 {
+        return self.u * a.x + self.v * a.y + self.w * a.z;
+    }
+
+    // The inverse of `local`.
+    pub fn to_local(&self, a: Vec3) -> Vec3 {
+        return Vec3 {
+            x: dot(a, self.u),
+            y: dot(a, self.v),
+            z: dot(a, self.w),
+        };
+    }
+}
@@ -0,0 +1,384 @@
+use super::background::*;
+use super::environment_map::*;
+use super::image_writer::*;
+use super::integrator::*;
+use super::rng::*;
+use super::scene::*;
+use super::sky::*;
+use super::vec3::*;
+use std::cmp::Ordering;
+use std::str::FromStr;
+use std::sync::Arc;
+
+pub const USAGE: &str = "Usage: raytracer [OPTIONS] [SCENE]
+
+Renders SCENE and writes the image. SCENE is a built-in scene (random,
+random-stone, simple or cornell) or the path of a scene description file,
+default random. Options given here override the settings of a scene file.
+
+Image:
+  -w, --width <PIXELS>        Image width [default: 1920]
+      --height <PIXELS>       Image height [default: 1200, or width / aspect ratio]
+      --aspect-ratio <RATIO>  Width over height, used when --height is not given,
+                              either a number or W:H [default: 16:10]
+  -s, --samples <COUNT>       Samples per pixel, must be a square number
+                              [default: 256]
+      --samples-x <COUNT>     Horizontal samples per pixel [default: 16]
+      --samples-y <COUNT>     Vertical samples per pixel [default: 16]
+  -d, --max-depth <BOUNCES>   Maximum ray depth [default: 50]
+      --roulette-depth <BOUNCES>
+                              Bounces after which paths carrying little light
+                              are ended at random by Russian roulette
+                              [default: off]
+      --integrator <NAME>     path, sampling the lights directly, or simple,
+                              the recursive tracer of the book [default: path]
+
+Camera:
+      --lookfrom <X,Y,Z>      Camera position [default: 13,2,3]
+      --lookat <X,Y,Z>        Point the camera looks at [default: 0,0,0]
+      --vup <X,Y,Z>           Camera up direction [default: 0,1,0]
+      --vfov <DEGREES>        Vertical field of view [default: 20]
+      --aperture <SIZE>       Lens aperture, 0 for a pinhole [default: 0.1]
+      --focus-dist <DIST>     Distance to the focal plane [default: 10]
+      --shutter-open <TIME>   Time the shutter opens [default: 0]
+      --shutter-close <TIME>  Time the shutter closes, later than it opens for
+                              motion blur [default: 0]
+
+Scene:
+      --background <COLOR>    Color of rays that miss everything: gradient for
+                              the default sky, sky for daylight with the sun
+                              45 degrees high, R,G,B like 0,0,0 for black, or
+                              the path of an equirectangular environment map
+                              (.hdr, .exr, .png or .ppm)
+
+Sampling:
+      --seed <SEED>           Random seed [default: 675248]
+      --rng <ALGORITHM>       Random number generator: pcg32 or small [default: pcg32]
+  -j, --threads <COUNT>       Worker threads, 0 for one per core [default: 0]
+
+Output:
+  -o, --output <PATH>         Output file, - for stdout [default: -]
+      --format <FORMAT>       Image format: png, ppm (binary), ppm-ascii, or the
+                              high dynamic range exr, hdr and pfm
+                              [default: from extension, else ppm-ascii]
+      --bit-depth <BITS>      Bits per channel, 8 or 16 for png and ppm, 16 (half)
+                              or 32 (float) for exr [default: 8, 32 for exr]
+
+  -h, --help                  Print this help
+";
+
+pub struct Options {
+    pub help: bool,
+    pub image_width: Option<i32>,
+    pub image_height: Option<i32>,
+    pub aspect_ratio: Option<f64>,
+    pub samples_per_pixel_x: Option<i32>,
+    pub samples_per_pixel_y: Option<i32>,
+    pub max_depth: Option<i32>,
+    pub roulette_depth: Option<i32>,
+    pub integrator: Option<IntegratorKind>,
+    pub lookfrom: Option<Point3>,
+    pub lookat: Option<Point3>,
+    pub vup: Option<Vec3>,
+    pub vfov: Option<f64>,
+    pub aperture: Option<f64>,
+    pub focus_dist: Option<f64>,
+    pub shutter_open: Option<f64>,
+    pub shutter_close: Option<f64>,
+    pub seed: Option<u64>,
+    pub background: Option<Background>,
+    pub rng_algorithm: RngAlgorithm,
+    pub threads: usize,
+    pub scene: String,
+    pub output: String,
+    pub format: OutputFormat,
+    pub bit_depth: u8,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            help: false,
+            image_width: None,
+            image_height: None,
+            aspect_ratio: None,
+            samples_per_pixel_x: None,
+            samples_per_pixel_y: None,
+            max_depth: None,
+            roulette_depth: None,
+            integrator: None,
+            lookfrom: None,
+            lookat: None,
+            vup: None,
+            vfov: None,
+            aperture: None,
+            focus_dist: None,
+            shutter_open: None,
+            shutter_close: None,
+            seed: None,
+            background: None,
+            rng_algorithm: RngAlgorithm::Pcg32,
+            threads: 0,
+            scene: String::from("random"),
+            output: String::from("-"),
+            format: OutputFormat::PpmAscii,
+            bit_depth: 8,
+        }
+    }
+}
+
+impl Options {
+    pub fn parse(args: &[String]) -> Result<Options, String> {
+        let mut options = Options::default();
+        let mut format: Option<OutputFormat> = None;
+        let mut bit_depth: Option<u8> = None;
+        let mut scene: Option<String> = None;
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            // Accept both "--flag value" and "--flag=value".
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+
+            if flag == "-h" || flag == "--help" {
+                options.help = true;
+                return Ok(options);
+            }
+
+            if !flag.starts_with('-') || flag == "-" {
+                if scene.is_some() {
+                    return Err(format!("unexpected argument '{}'", arg));
+                }
+                scene = Some(arg.clone());
+                continue;
+            }
+
+            let mut value = || -> Result<String, String> {
+                if let Some(value) = inline_value.clone() {
+                    return Ok(value);
+                }
+                return iter
+                    .next()
+                    .cloned()
+                    .ok_or(format!("missing value for {}", flag));
+            };
+
+            match flag {
+                "-w" | "--width" => options.image_width = Some(parse_positive(flag, &value()?)?),
+                "--height" => options.image_height = Some(parse_positive(flag, &value()?)?),
+                "--aspect-ratio" => {
+                    options.aspect_ratio = Some(parse_aspect_ratio(flag, &value()?)?)
+                }
+                "-s" | "--samples" => {
+                    let samples: i32 = parse_positive(flag, &value()?)?;
+                    let side = (samples as f64).sqrt().round() as i32;
+                    if side.checked_mul(side) != Some(samples) {
+                        return Err(format!(
+                            "invalid value '{}' for {}: expected a square number, use --samples-x and --samples-y for other grids",
+                            samples, flag
+                        ));
+                    }
+                    options.samples_per_pixel_x = Some(side);
+                    options.samples_per_pixel_y = Some(side);
+                }
+                "--samples-x" => {
+                    options.samples_per_pixel_x = Some(parse_positive(flag, &value()?)?)
+                }
+                "--samples-y" => {
+                    options.samples_per_pixel_y = Some(parse_positive(flag, &value()?)?)
+                }
+                "-d" | "--max-depth" => options.max_depth = Some(parse_positive(flag, &value()?)?),
+                "--roulette-depth" => {
+                    options.roulette_depth = Some(parse_non_negative(flag, &value()?)?)
+                }
+                "--integrator" => {
+                    let name = value()?;
+                    options.integrator = Some(IntegratorKind::from_name(&name).ok_or(format!(
+                        "invalid value '{}' for {}: expected path or simple",
+                        name, flag
+                    ))?);
+                }
+                "--lookfrom" => options.lookfrom = Some(parse_vec3(flag, &value()?)?),
+                "--lookat" => options.lookat = Some(parse_vec3(flag, &value()?)?),
+                "--vup" => options.vup = Some(parse_vec3(flag, &value()?)?),
+                "--vfov" => options.vfov = Some(parse_positive(flag, &value()?)?),
+                "--aperture" => options.aperture = Some(parse_non_negative(flag, &value()?)?),
+                "--focus-dist" => options.focus_dist = Some(parse_positive(flag, &value()?)?),
+                "--shutter-open" => options.shutter_open = Some(parse_number(flag, &value()?)?),
+                "--shutter-close" => options.shutter_close = Some(parse_number(flag, &value()?)?),
+                "--background" => {
+                    let value = value()?;
+                    options.background = Some(if value == "gradient" {
+                        Background::Gradient
+                    } else if value == "sky" {
+                        Background::Sky(Sky::create(45.0, 0.0, 3.0, 1.0))
+                    } else if value.contains(',') {
+                        Background::Solid(parse_vec3(flag, &value)?)
+                    } else {
+                        let map = EnvironmentMap::load(&value, 0.0, 1.0)
+                            .map_err(|error| format!("invalid value for {}: {}", flag, error))?;
+                        Background::Map(Arc::new(map))
+                    });
+                }
+                "--seed" => options.seed = Some(parse_number(flag, &value()?)?),
+                "--rng" => {
+                    let name = value()?;
+                    options.rng_algorithm = RngAlgorithm::from_name(&name).ok_or(format!(
+                        "invalid value '{}' for {}: expected pcg32 or small",
+                        name, flag
+                    ))?;
+                }
+                "-j" | "--threads" => options.threads = parse_number(flag, &value()?)?,
+                "-o" | "--output" => options.output = value()?,
+                "--format" => {
+                    let name = value()?;
+                    format = Some(OutputFormat::from_name(&name).ok_or(format!(
+                        "invalid value '{}' for {}: expected png, ppm, ppm-ascii, exr, hdr or pfm",
+                        name, flag
+                    ))?);
+                }
+                "--bit-depth" => bit_depth = Some(parse_positive(flag, &value()?)?),
+                _ => return Err(format!("unknown option '{}'", flag)),
+            }
+        }
+
+        if let Some(scene) = scene {
+            options.scene = scene;
+        }
+
+        options.format = match format {
+            Some(format) => format,
+            None => OutputFormat::from_path(&options.output).unwrap_or(OutputFormat::PpmAscii),
+        };
+
+        let bit_depths = options.format.bit_depths();
+        options.bit_depth = bit_depth.unwrap_or(bit_depths[0]);
+        if !bit_depths.contains(&options.bit_depth) {
+            let supported: Vec<String> = bit_depths.iter().map(|bits| bits.to_string()).collect();
+            return Err(format!(
+                "invalid value '{}' for --bit-depth: the output format supports {}",
+                options.bit_depth,
+                supported.join(" or ")
+            ));
+        }
+
+        return Ok(options);
+    }
+
+    // Options given on the command line take precedence over the scene.
+    pub fn apply(&self, scene: &mut Scene) {
+        let settings = &mut scene.settings;
+        let camera = &mut scene.camera;
+
+        match (self.image_width, self.image_height) {
+            (Some(width), Some(height)) => {
+                settings.image_width = width;
+                settings.image_height = height;
+            }
+            (Some(width), None) => {
+                let aspect_ratio = self.aspect_ratio.unwrap_or(settings.aspect_ratio());
+                settings.image_width = width;
+                settings.image_height = (width as f64 / aspect_ratio) as i32;
+            }
+            (None, Some(height)) => {
+                let aspect_ratio = self.aspect_ratio.unwrap_or(settings.aspect_ratio());
+                settings.image_width = (height as f64 * aspect_ratio) as i32;
+                settings.image_height = height;
+            }
+            (None, None) => {
+                if let Some(aspect_ratio) = self.aspect_ratio {
+                    settings.image_height = (settings.image_width as f64 / aspect_ratio) as i32;
+                }
+            }
+        }
+
+        settings.samples_per_pixel_x = self
+            .samples_per_pixel_x
+            .unwrap_or(settings.samples_per_pixel_x);
+        settings.samples_per_pixel_y = self
+            .samples_per_pixel_y
+            .unwrap_or(settings.samples_per_pixel_y);
+        settings.max_depth = self.max_depth.unwrap_or(settings.max_depth);
+        if self.roulette_depth.is_some() {
+            settings.roulette_depth = self.roulette_depth;
+        }
+        settings.integrator = self.integrator.unwrap_or(settings.integrator);
+        settings.seed = self.seed.unwrap_or(settings.seed);
+
+        camera.lookfrom = self.lookfrom.unwrap_or(camera.lookfrom);
+        camera.lookat = self.lookat.unwrap_or(camera.lookat);
+        camera.vup = self.vup.unwrap_or(camera.vup);
+        camera.vfov = self.vfov.unwrap_or(camera.vfov);
+        camera.aperture = self.aperture.unwrap_or(camera.aperture);
+        camera.focus_dist = self.focus_dist.unwrap_or(camera.focus_dist);
+        camera.shutter_open = self.shutter_open.unwrap_or(camera.shutter_open);
+        camera.shutter_close = self.shutter_close.unwrap_or(camera.shutter_close);
+
+        if let Some(background) = &self.background {
+            scene.background = background.clone();
+        }
+    }
+}
+
+fn parse_number<T: FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    return value
+        .trim()
+        .parse::<T>()
+        .map_err(|_| format!("invalid value '{}' for {}: expected a number", value, flag));
+}
+
+fn parse_positive<T: FromStr + PartialOrd + Default>(flag: &str, value: &str) -> Result<T, String> {
+    let number: T = parse_number(flag, value)?;
+    if number.partial_cmp(&T::default()) != Some(Ordering::Greater) {
+        return Err(format!(
+            "invalid value '{}' for {}: expected a positive number",
+            value, flag
+        ));
+    }
+    return Ok(number);
+}
+
+fn parse_non_negative<T: FromStr + PartialOrd + Default>(
+    flag: &str,
+    value: &str,
+) -> Result<T, String> {
+    let number: T = parse_number(flag, value)?;
+    if number
+        .partial_cmp(&T::default())
+        .is_none_or(Ordering::is_lt)
+    {
+        return Err(format!(
+            "invalid value '{}' for {}: expected a non-negative number",
+            value, flag
+        ));
+    }
+    return Ok(number);
+}
+
+fn parse_aspect_ratio(flag: &str, value: &str) -> Result<f64, String> {
+    return match value.split_once(':') {
+        Some((width, height)) => {
+            let width: f64 = parse_positive(flag, width)?;
+            let height: f64 = parse_positive(flag, height)?;
+            Ok(width / height)
+        }
+        None => parse_positive(flag, value),
+    };
+}
+
+fn parse_vec3(flag: &str, value: &str) -> Result<Vec3, String> {
+    let components: Vec<&str> = value.split(',').collect();
+    if components.len() != 3 {
+        return Err(format!(
+            "invalid value '{}' for {}: expected three comma separated numbers like 1,2,3",
+            value, flag
+        ));
+    }
+    return Ok(Vec3 {
+        x: parse_number(flag, components[0])?,
+        y: parse_number(flag, components[1])?,
+        z: parse_number(flag, components[2])?,
+    });
+}
@@ -0,0 +1,117 @@
+use super::rng::*;
+use super::vec3::*;
+
+const POINT_COUNT: usize = 256;
+
+// Gradient (Perlin) noise with random unit gradients on a lattice, hashed by
+// three random permutation tables. The tables come from the given generator,
+// so the same seed always gives the same noise.
+pub struct Perlin {
+    gradients: Vec<Vec3>,
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>,
+}
+
+impl Perlin {
+    pub fn create(rng: &mut RandomNumberGenerator) -> Perlin {
+        let gradients = (0..POINT_COUNT)
+            .map(|_| unit_vector(rng.random_in_unit_sphere()))
+            .collect();
+        return Perlin {
+            gradients,
+            perm_x: generate_perm(rng),
+            perm_y: generate_perm(rng),
+            perm_z: generate_perm(rng),
+        };
+    }
+
+    // Smoothly varying noise in about [-1, 1].
+    pub fn noise(&self, p: Point3) -> f64 {
+        let u = p.x - p.x.floor();
+        let v = p.y - p.y.floor();
+        let w = p.z - p.z.floor();
+        let i = p.x.floor() as i64;
+        let j = p.y.floor() as i64;
+        let k = p.z.floor() as i64;
+
+        let mut c = [[[Vec3::default(); 2]; 2]; 2];
+        for (di, plane) in c.iter_mut().enumerate() {
+            for (dj, row) in plane.iter_mut().enumerate() {
+                for (dk, gradient) in row.iter_mut().enumerate() {
+                    let index = self.perm_x[((i + di as i64) & 255) as usize]
+                        ^ self.perm_y[((j + dj as i64) & 255) as usize]
+                        ^ self.perm_z[((k + dk as i64) & 255) as usize];
+                    *gradient = self.gradients[index];
+                }
+            }
+        }
+        return perlin_interp(&c, u, v, w);
+    }
+
+    // Sum of the absolute noise over `depth` octaves, each of double the
+    // frequency and half the amplitude of the previous one.
+    pub fn turbulence(&self, p: Point3, depth: i32) -> f64 {
+        let mut accum = 0.0;
+        let mut temp_p = p;
+        let mut weight = 1.0;
+        for _ in 0..depth {
+            accum += weight * self.noise(temp_p).abs();
+            weight *= 0.5;
+            temp_p = temp_p * 2.0;
+        }
+        return accum;
+    }
+
+    // Fractional Brownian motion: like turbulence but summing the signed
+    // noise, which gives softer, cloud-like variation around 0.
+    pub fn fbm(&self, p: Point3, octaves: i32) -> f64 {
+        let mut accum = 0.0;
+        let mut temp_p = p;
+        let mut weight = 1.0;
+        for _ in 0..octaves {
+            accum += weight * self.noise(temp_p);
+            weight *= 0.5;
+            temp_p = temp_p * 2.0;
+        }
+        return accum;
+    }
+}
+
+fn generate_perm(rng: &mut RandomNumberGenerator) -> Vec<usize> {
+    let mut p: Vec<usize> = (0..POINT_COUNT).collect();
+    for i in (1..POINT_COUNT).rev() {
+        let target = rng.random_int(0, i as i32) as usize;
+        p.swap(i, target);
+    }
+    return p;
+}
+
+// Trilinear interpolation of the gradient dot products with Hermite
+// smoothing, which hides the lattice.
+fn perlin_interp(c: &[[[Vec3; 2]; 2]; 2], u: f64, v: f64, w: f64) -> f64 {
+    let uu = u * u * (3.0 - 2.0 * u);
+    let vv = v * v * (3.0 - 2.0 * v);
+    let ww = w * w * (3.0 - 2.0 * w);
+
+    let mut accum = 0.0;
+    for (i, plane) in c.iter().enumerate() {
+        for (j, row) in plane.iter().enumerate() {
+            for (k, gradient) in row.iter().enumerate() {
+                let fi = i as f64;
+                let fj = j as f64;
+                let fk = k as f64;
+                let weight = Vec3 {
+                    x: u - fi,
+                    y: v - fj,
+                    z: w - fk,
+                };
+                accum += (fi * uu + (1.0 - fi) * (1.0 - uu))
+                    * (fj * vv + (1.0 - fj) * (1.0 - vv))
+                    * (fk * ww + (1.0 - fk) * (1.0 - ww))
+                    * dot(*gradient, weight);
+            }
+        }
+    }
+    return accum;
+}
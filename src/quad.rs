@@ -0,0 +1,139 @@
+use super::aabb::*;
+use super::hit_record::*;
+use super::hittable::*;
+use super::material::*;
+use super::ray::*;
+use super::rng::*;
+use super::vec3::*;
+use std::sync::Arc;
+
+// A parallelogram with corner `q` and edges `u` and `v`. The outward normal is
+// u x v, and the hit coordinates (u, v) are the position along each edge.
+pub struct Quad {
+    pub q: Point3,
+    pub u: Vec3,
+    pub v: Vec3,
+    pub material: Arc<dyn Material>,
+    normal: Vec3,
+    d: f64,
+    w: Vec3,
+}
+
+impl Quad {
+    pub fn create(q: Point3, u: Vec3, v: Vec3, material: Arc<dyn Material>) -> Quad {
+        let n = cross(u, v);
+        let normal = unit_vector(n);
+        return Quad {
+            q,
+            u,
+            v,
+            material,
+            normal,
+            d: dot(normal, q),
+            w: n / dot(n, n),
+        };
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(
+        &self,
+        _: &mut RandomNumberGenerator,
+        r: Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+    ) -> bool {
+        let denom = dot(self.normal, r.direction);
+
+        // No hit if the ray is parallel to the plane.
+        if denom.abs() < 1e-8 {
+            return false;
+        }
+
+        let t = (self.d - dot(self.normal, r.origin)) / denom;
+        if t < t_min || t_max < t {
+            return false;
+        }
+
+        // Express the hit point in the plane's (u, v) frame and check that it
+        // lies inside the parallelogram.
+        let intersection = r.at(t);
+        let planar_hitpt_vector = intersection - self.q;
+        let alpha = dot(self.w, cross(planar_hitpt_vector, self.v));
+        let beta = dot(self.w, cross(self.u, planar_hitpt_vector));
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return false;
+        }
+
+        rec.t = t;
+        rec.p = intersection;
+        rec.u = alpha;
+        rec.v = beta;
+        rec.set_face_normal(r, self.normal);
+        rec.material = self.material.clone();
+
+        return true;
+    }
+
+    fn bounding_box(&self, output_box: &mut Aabb) -> bool {
+        let mut bbox = Aabb::default();
+        for corner in [
+            self.q,
+            self.q + self.u,
+            self.q + self.v,
+            self.q + self.u + self.v,
+        ] {
+            bbox = surrounding_point(bbox, corner);
+        }
+        *output_box = bbox.pad();
+        return true;
+    }
+
+    fn pdf_value(&self, rng: &mut RandomNumberGenerator, origin: Point3, direction: Vec3) -> f64 {
+        return parallelogram_pdf_value(self, rng, self.u, self.v, origin, direction);
+    }
+
+    fn random(&self, rng: &mut RandomNumberGenerator, origin: Point3) -> Vec3 {
+        return random_on_parallelogram(rng, self.q, self.u, self.v, origin);
+    }
+}
+
+// Light sampling of a parallelogram with corner `q` and edges `u` and `v`,
+// shared by quads and the axis-aligned rectangles: `random` picks a uniformly
+// random point on it, and `pdf_value` converts that area density to one per
+// solid angle as seen from `origin`, where `shape` is the parallelogram.
+pub fn parallelogram_pdf_value(
+    shape: &dyn Hittable,
+    rng: &mut RandomNumberGenerator,
+    u: Vec3,
+    v: Vec3,
+    origin: Point3,
+    direction: Vec3,
+) -> f64 {
+    let mut rec = HitRecord::new();
+    let r = Ray {
+        origin,
+        direction,
+        time: 0.0,
+    };
+    if !shape.hit(rng, r, 0.001, f64::INFINITY, &mut rec) {
+        return 0.0;
+    }
+
+    // The length of u x v is the area and its direction the normal.
+    let distance_squared = rec.t * rec.t * direction.length_squared();
+    let cosine_times_area = (dot(direction, cross(u, v)) / direction.length()).abs();
+    return distance_squared / cosine_times_area;
+}
+
+pub fn random_on_parallelogram(
+    rng: &mut RandomNumberGenerator,
+    q: Point3,
+    u: Vec3,
+    v: Vec3,
+    origin: Point3,
+) -> Vec3 {
+    let p = q + u * rng.random_double() + v * rng.random_double();
+    return p - origin;
+}
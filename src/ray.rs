@@ -1,9 +1,10 @@
 use super::vec3::*;
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Default)]
 pub struct Ray {
     pub origin: Point3,
     pub direction: Vec3,
+    pub time: f64,
 }
 
 impl Ray {
@@ -11,12 +12,3 @@ impl Ray {
         return self.origin + self.direction * t;
     }
 }
-
-impl Default for Ray {
-    fn default() -> Ray {
-        Ray {
-            origin: Point3::default(),
-            direction: Vec3::default(),
-        }
-    }
-}
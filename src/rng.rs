@@ -1,45 +1,129 @@
 use super::vec3::*;
 use rand::rngs::SmallRng;
-use rand::SeedableRng;
+use rand::{Rng, SeedableRng};
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum RngAlgorithm {
+    Pcg32,
+    SmallRng,
+}
+
+impl RngAlgorithm {
+    pub fn from_name(name: &str) -> Option<RngAlgorithm> {
+        return match name {
+            "pcg32" => Some(RngAlgorithm::Pcg32),
+            "small" => Some(RngAlgorithm::SmallRng),
+            _ => None,
+        };
+    }
+}
+
+// PCG-XSH-RR with 64-bit state and a selectable stream, see https://www.pcg-random.org
+#[derive(Copy, Clone)]
+struct Pcg32 {
+    state: u64,
+    inc: u64,
+}
+
+impl Pcg32 {
+    const MULTIPLIER: u64 = 6364136223846793005;
+
+    fn create(initstate: u64, initseq: u64) -> Pcg32 {
+        let mut pcg = Pcg32 {
+            state: 0,
+            inc: (initseq << 1) | 1,
+        };
+        pcg.next_u32();
+        pcg.state = pcg.state.wrapping_add(initstate);
+        pcg.next_u32();
+        return pcg;
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        let oldstate = self.state;
+        self.state = oldstate
+            .wrapping_mul(Pcg32::MULTIPLIER)
+            .wrapping_add(self.inc);
+        let xorshifted = (((oldstate >> 18) ^ oldstate) >> 27) as u32;
+        let rot = (oldstate >> 59) as u32;
+        return xorshifted.rotate_right(rot);
+    }
+}
+
+#[derive(Clone)]
+enum Generator {
+    Pcg32(Pcg32),
+    SmallRng(SmallRng),
+}
 
 #[derive(Clone)]
 pub struct RandomNumberGenerator {
-    pub state: u64,
-    pub div: u64,
-    pub modulo: u64,
-    pub small_rng: SmallRng,
+    pub seed: u64,
+    pub algorithm: RngAlgorithm,
+    generator: Generator,
+}
+
+// SplitMix64 finalizer, used to turn structured keys into well mixed seeds.
+fn mix64(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    return z ^ (z >> 31);
 }
 
 impl RandomNumberGenerator {
-    pub fn create() -> RandomNumberGenerator {
+    pub fn create(seed: u64, algorithm: RngAlgorithm) -> RandomNumberGenerator {
+        return RandomNumberGenerator::from_key(seed, algorithm, mix64(seed));
+    }
+
+    fn from_key(seed: u64, algorithm: RngAlgorithm, key: u64) -> RandomNumberGenerator {
+        let generator = match algorithm {
+            RngAlgorithm::Pcg32 => Generator::Pcg32(Pcg32::create(key, mix64(key))),
+            RngAlgorithm::SmallRng => Generator::SmallRng(SmallRng::seed_from_u64(key)),
+        };
         return RandomNumberGenerator {
-            state: 675248,
-            div: 1000,
-            modulo: 1000000,
-            small_rng: SmallRng::seed_from_u64(1),
+            seed,
+            algorithm,
+            generator,
         };
     }
 
-    #[allow(dead_code)]
-    fn next(&mut self) -> u64 {
-        self.state = self.state * self.state / self.div % self.modulo;
-        return self.state;
+    // Returns an independent stream for one sample of one pixel. It depends
+    // only on the seed and the indices, never on how much of this generator
+    // has been consumed, so renders are reproducible with any thread count.
+    pub fn for_sample(&self, pixel: u64, sample: u64) -> RandomNumberGenerator {
+        let key = mix64(mix64(mix64(self.seed) ^ pixel) ^ sample);
+        return RandomNumberGenerator::from_key(self.seed, self.algorithm, key);
     }
 
-    pub fn random_double(&mut self) -> f64 {
-        // use this version if you want actual PRNG
-        // return self.small_rng.gen();
+    // Returns an independent stream for another use of the same seed, such
+    // as building part of a scene, told apart by the constant `stream`.
+    pub fn for_stream(&self, stream: u64) -> RandomNumberGenerator {
+        let key = mix64(mix64(self.seed).rotate_left(32) ^ mix64(stream));
+        return RandomNumberGenerator::from_key(self.seed, self.algorithm, key);
+    }
 
-        // use this version if you want repeatable sequence of numbers
-        let t1 = self.next();
-        let t2 = self.next();
-        return ((t1 * self.modulo + t2) as f64) / (self.modulo as f64) / (self.modulo as f64);
+    pub fn random_double(&mut self) -> f64 {
+        return match &mut self.generator {
+            Generator::Pcg32(pcg) => {
+                // 53 random bits fill the mantissa of a double in [0, 1)
+                let hi = (pcg.next_u32() as u64) << 21;
+                let lo = (pcg.next_u32() >> 11) as u64;
+                ((hi | lo) as f64) / ((1u64 << 53) as f64)
+            }
+            Generator::SmallRng(small_rng) => small_rng.gen(),
+        };
     }
 
     pub fn random_range(&mut self, min: f64, max: f64) -> f64 {
         return min + (max - min) * self.random_double();
     }
 
+    // A random integer in [min, max].
+    pub fn random_int(&mut self, min: i32, max: i32) -> i32 {
+        return self.random_range(min as f64, (max + 1) as f64) as i32;
+    }
+
     pub fn random_vec3(&mut self) -> Vec3 {
         return Vec3 {
             x: self.random_double(),
@@ -84,3 +168,33 @@ impl RandomNumberGenerator {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn draw(rng: &mut RandomNumberGenerator) -> Vec<f64> {
+        return (0..8).map(|_| rng.random_double()).collect();
+    }
+
+    #[test]
+    fn sample_streams_do_not_depend_on_the_parent_state() {
+        for algorithm in [RngAlgorithm::Pcg32, RngAlgorithm::SmallRng] {
+            let fresh = RandomNumberGenerator::create(7, algorithm);
+            let mut used = RandomNumberGenerator::create(7, algorithm);
+            draw(&mut used);
+            assert_eq!(
+                draw(&mut fresh.for_sample(3, 5)),
+                draw(&mut used.for_sample(3, 5))
+            );
+            assert_ne!(
+                draw(&mut fresh.for_sample(3, 5)),
+                draw(&mut fresh.for_sample(3, 6))
+            );
+            assert_ne!(
+                draw(&mut fresh.for_sample(3, 5)),
+                draw(&mut RandomNumberGenerator::create(8, algorithm).for_sample(3, 5))
+            );
+        }
+    }
+}
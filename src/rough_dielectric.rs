@@ -0,0 +1,313 @@
+use super::dielectric::*;
+use super::hit_record::*;
+use super::material::*;
+use super::microfacet::*;
+use super::ray::*;
+use super::rng::*;
+use super::vec3::*;
+
+// Glass with a GGX microfacet surface that both reflects and refracts, for
+// frosted and etched glass (Walter et al., "Microfacet Models for Refraction
+// through Rough Surfaces", 2007). The split between the two follows the
+// exact Fresnel equations rather than Schlick's approximation. Like
+// `Dielectric`, the light is not scaled by the squared ratio of the indices
+// as it crosses the surface, and light inside is absorbed at the rate
+// `absorption` per unit of distance.
+pub struct RoughDielectric {
+    pub ir: f64,
+    pub distribution: Ggx,
+    pub absorption: Color,
+}
+
+impl RoughDielectric {
+    pub fn create(ir: f64, roughness: f64, absorption: Color) -> RoughDielectric {
+        return RoughDielectric {
+            ir,
+            distribution: Ggx::from_roughness(roughness),
+            absorption,
+        };
+    }
+
+    // The index of the side the ray enters over the one it comes from.
+    fn eta(&self, rec: &HitRecord) -> f64 {
+        return if rec.front_face {
+            self.ir
+        } else {
+            1.0 / self.ir
+        };
+    }
+
+    // The microfacet normal that takes the incoming ray to `direction`, with
+    // the cosines of both rays to the normal, the cosine of the incoming ray
+    // to the microfacet normal, and d(normal) / d(direction). None when no
+    // microfacet can do it.
+    fn lobe(
+        &self,
+        r_in: Ray,
+        rec: &HitRecord,
+        direction: Vec3,
+    ) -> Option<(Vec3, f64, f64, f64, f64)> {
+        if self.distribution.is_smooth() {
+            return None;
+        }
+        let wo = -unit_vector(r_in.direction);
+        let wi = unit_vector(direction);
+        let cos_o = dot(wo, rec.normal);
+        let cos_i = dot(wi, rec.normal);
+        if cos_o <= 0.0 || cos_i == 0.0 {
+            return None;
+        }
+
+        let eta = self.eta(rec);
+        let mut h = if cos_i > 0.0 { wo + wi } else { wo + wi * eta };
+        if near_zero(h) {
+            return None;
+        }
+        h = unit_vector(h);
+        if dot(h, rec.normal) < 0.0 {
+            h = -h;
+        }
+        let o_dot_h = dot(wo, h);
+        let i_dot_h = dot(wi, h);
+        // Microfacets facing away from either ray cannot connect them.
+        if o_dot_h <= 0.0 || (cos_i > 0.0) != (i_dot_h > 0.0) {
+            return None;
+        }
+
+        let jacobian = if cos_i > 0.0 {
+            1.0 / (4.0 * o_dot_h)
+        } else {
+            let denominator = i_dot_h + o_dot_h / eta;
+            -i_dot_h / (denominator * denominator)
+        };
+        return Some((h, cos_o, cos_i, o_dot_h, jacobian));
+    }
+}
+
+// The exact reflectance of unpolarized light arriving at the angle whose
+// cosine is `cos_i` on a surface with relative index `eta`, 1 for total
+// internal reflection.
+fn fresnel_dielectric(cos_i: f64, eta: f64) -> f64 {
+    let sin_t_squared = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin_t_squared >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin_t_squared).sqrt();
+    let r_parallel = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let r_perpendicular = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    return (r_parallel * r_parallel + r_perpendicular * r_perpendicular) / 2.0;
+}
+
+impl Material for RoughDielectric {
+    // Rays the surface does not scatter are absorbed.
+    fn diffuse(&self, _: &HitRecord) -> Color {
+        return Color {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        };
+    }
+
+    // Picks a microfacet normal among the ones seen from the incoming ray,
+    // then reflects about it or refracts through it as often as the Fresnel
+    // equations say.
+    fn scatter(
+        &self,
+        rng: &mut RandomNumberGenerator,
+        r_in: Ray,
+        rec: &HitRecord,
+        attenuation: &mut Color,
+        scattered: &mut Ray,
+    ) -> bool {
+        let unit_direction = unit_vector(r_in.direction);
+        let cos_o = -dot(unit_direction, rec.normal);
+        if cos_o <= 0.0 {
+            return false;
+        }
+        let eta = self.eta(rec);
+
+        let h = if self.distribution.is_smooth() {
+            rec.normal
+        } else {
+            self.distribution
+                .sample_visible_normal(rng, rec.normal, -unit_direction)
+        };
+        let o_dot_h = -dot(unit_direction, h);
+        let reflects = rng.random_double() < fresnel_dielectric(o_dot_h, eta);
+        let direction = if reflects {
+            reflect(unit_direction, h)
+        } else {
+            refract(unit_direction, h, 1.0 / eta)
+        };
+        // The reflected ray must stay on the side it came from, and the
+        // refracted one must cross the surface.
+        let cos_i = dot(direction, rec.normal);
+        if (reflects && cos_i <= 0.0) || (!reflects && cos_i >= 0.0) {
+            return false;
+        }
+
+        *scattered = Ray {
+            origin: rec.p,
+            direction,
+            time: r_in.time,
+        };
+        let mut masking = 1.0;
+        if !self.distribution.is_smooth() {
+            masking = self.distribution.g2(cos_o, cos_i.abs()) / self.distribution.g1(cos_o);
+        }
+        *attenuation = transmittance(self.absorption, r_in, rec) * masking;
+        return true;
+    }
+
+    fn is_specular(&self) -> bool {
+        return self.distribution.is_smooth();
+    }
+
+    // Inside the glass, this includes the light absorbed on the way back
+    // along the incoming ray, as the scattered rays do.
+    fn eval(&self, r_in: Ray, rec: &HitRecord, direction: Vec3) -> Color {
+        let value = match self.lobe(r_in, rec, direction) {
+            Some((h, cos_o, cos_i, o_dot_h, jacobian)) => {
+                let eta = self.eta(rec);
+                let fresnel = fresnel_dielectric(o_dot_h, eta);
+                let chance = if cos_i > 0.0 { fresnel } else { 1.0 - fresnel };
+                let d = self.distribution.d(dot(h, rec.normal));
+                let g = self.distribution.g2(cos_o, cos_i.abs());
+                chance * d * g * o_dot_h * jacobian / cos_o
+            }
+            None => 0.0,
+        };
+        return transmittance(self.absorption, r_in, rec) * value;
+    }
+
+    // The density of the visible normal that reflects or refracts the
+    // incoming ray into `direction`, times the chance of doing so, times
+    // the change of solid angle from the normal to `direction`.
+    fn pdf(&self, r_in: Ray, rec: &HitRecord, direction: Vec3) -> f64 {
+        return match self.lobe(r_in, rec, direction) {
+            Some((h, cos_o, cos_i, o_dot_h, jacobian)) => {
+                let fresnel = fresnel_dielectric(o_dot_h, self.eta(rec));
+                let chance = if cos_i > 0.0 { fresnel } else { 1.0 - fresnel };
+                let cos_h = dot(h, rec.normal);
+                chance * self.distribution.visible_normal_pdf(cos_o, cos_h, o_dot_h) * jacobian
+            }
+            None => 0.0,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A hit at the origin on a surface facing +z, from the side given by
+    // `front_face`, by a ray arriving at the angle whose cosine is `cos_o`
+    // from two units away.
+    fn hit(cos_o: f64, front_face: bool) -> (Ray, HitRecord) {
+        let direction = Vec3 {
+            x: f64::sqrt(1.0 - cos_o * cos_o),
+            y: 0.0,
+            z: -cos_o,
+        };
+        let r_in = Ray {
+            origin: -direction * 2.0,
+            direction,
+            time: 0.0,
+        };
+        let mut rec = HitRecord::new();
+        rec.normal = Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: 1.0,
+        };
+        rec.front_face = front_face;
+        rec.t = 2.0;
+        return (r_in, rec);
+    }
+
+    // A direction picked by `scatter`, refracted or reflected, from outside
+    // or inside the glass, is weighted by what `eval` over `pdf` gives for it.
+    #[test]
+    fn the_scattered_weight_is_eval_over_pdf() {
+        let absorption = Color {
+            x: 0.1,
+            y: 0.2,
+            z: 0.3,
+        };
+        let mut rng = RandomNumberGenerator::create(3141, RngAlgorithm::Pcg32);
+        let mut refracted = 0;
+        for roughness in [0.2, 0.5, 0.9] {
+            let glass = RoughDielectric::create(1.5, roughness, absorption);
+            for cos_o in [0.95, 0.6, 0.15] {
+                for front_face in [true, false] {
+                    let (r_in, rec) = hit(cos_o, front_face);
+                    for _ in 0..100 {
+                        let mut attenuation = Color::default();
+                        let mut scattered = Ray::default();
+                        if !glass.scatter(&mut rng, r_in, &rec, &mut attenuation, &mut scattered) {
+                            continue;
+                        }
+                        if dot(scattered.direction, rec.normal) < 0.0 {
+                            refracted += 1;
+                        }
+                        let pdf = glass.pdf(r_in, &rec, scattered.direction);
+                        assert!(pdf > 0.0);
+                        let weight = glass.eval(r_in, &rec, scattered.direction) / pdf;
+                        for (a, b) in [
+                            (weight.x, attenuation.x),
+                            (weight.y, attenuation.y),
+                            (weight.z, attenuation.z),
+                        ] {
+                            assert!((a - b).abs() < 1e-9 * b.max(1.0), "{} != {}", a, b);
+                        }
+                    }
+                }
+            }
+        }
+        assert!(refracted > 0);
+    }
+
+    // Nearly smooth glass splits the light between reflection and refraction
+    // as the Fresnel equations do, and creates none, from either side.
+    #[test]
+    fn nearly_smooth_glass_conserves_energy() {
+        let clear = Color {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        };
+        let glass = RoughDielectric::create(1.5, 0.05, clear);
+        let mut rng = RandomNumberGenerator::create(1414, RngAlgorithm::Pcg32);
+        for cos_o in [0.99, 0.8, 0.5, 0.2] {
+            for front_face in [true, false] {
+                let (r_in, rec) = hit(cos_o, front_face);
+                let samples = 20000;
+                let mut reflectance = 0.0;
+                let mut transmittance = 0.0;
+                for _ in 0..samples {
+                    let mut attenuation = Color::default();
+                    let mut scattered = Ray::default();
+                    if !glass.scatter(&mut rng, r_in, &rec, &mut attenuation, &mut scattered) {
+                        continue;
+                    }
+                    if dot(scattered.direction, rec.normal) > 0.0 {
+                        reflectance += attenuation.x / samples as f64;
+                    } else {
+                        transmittance += attenuation.x / samples as f64;
+                    }
+                }
+                assert!(reflectance + transmittance <= 1.0 + 1e-9);
+                assert!(reflectance + transmittance > 0.98);
+                let fresnel = fresnel_dielectric(cos_o, glass.eta(&rec));
+                assert!(
+                    (reflectance - fresnel).abs() < 0.01,
+                    "cos_o {} front_face {}: {} != {}",
+                    cos_o,
+                    front_face,
+                    reflectance,
+                    fresnel
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,228 @@
+use super::background::*;
+use super::camera::*;
+use super::delta_light::*;
+use super::hittable_list::*;
+use super::integrator::*;
+use super::vec3::*;
+use std::sync::Arc;
+
+#[derive(Copy, Clone)]
+pub struct CameraSettings {
+    pub lookfrom: Point3,
+    pub lookat: Point3,
+    pub vup: Vec3,
+    pub vfov: f64, // vertical field-of-view in degrees
+    pub aperture: f64,
+    pub focus_dist: f64,
+    pub shutter_open: f64,
+    pub shutter_close: f64,
+}
+
+impl Default for CameraSettings {
+    fn default() -> CameraSettings {
+        CameraSettings {
+            lookfrom: Point3 {
+                x: 13.0,
+                y: 2.0,
+                z: 3.0,
+            },
+            lookat: Point3 {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+            },
+            vup: Vec3 {
+                x: 0.0,
+                y: 1.0,
+                z: 0.0,
+            },
+            vfov: 20.0,
+            aperture: 0.1,
+            focus_dist: 10.0,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
+        }
+    }
+}
+
+impl CameraSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if near_zero(cross(self.vup, self.lookfrom - self.lookat)) {
+            return Err(String::from(
+                "the camera up direction must not be parallel to the view direction, and lookfrom must differ from lookat",
+            ));
+        }
+        if !(self.vfov > 0.0 && self.vfov < 180.0) {
+            return Err(format!(
+                "the vertical field of view must be between 0 and 180 degrees, got {}",
+                self.vfov
+            ));
+        }
+        if !(0.0..).contains(&self.aperture) {
+            return Err(format!(
+                "the aperture must not be negative, got {}",
+                self.aperture
+            ));
+        }
+        if self.focus_dist.is_nan() || self.focus_dist <= 0.0 {
+            return Err(format!(
+                "the focus distance must be positive, got {}",
+                self.focus_dist
+            ));
+        }
+        if self.shutter_close < self.shutter_open {
+            return Err(format!(
+                "the shutter must close after it opens, got {} to {}",
+                self.shutter_open, self.shutter_close
+            ));
+        }
+        return Ok(());
+    }
+
+    pub fn create_camera(&self, aspect_ratio: f64) -> Camera {
+        return Camera::create(
+            self.lookfrom,
+            self.lookat,
+            self.vup,
+            self.vfov,
+            aspect_ratio,
+            self.aperture,
+            self.focus_dist,
+            self.shutter_open,
+            self.shutter_close,
+        );
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct RenderSettings {
+    pub image_width: i32,
+    pub image_height: i32,
+    pub samples_per_pixel_x: i32,
+    pub samples_per_pixel_y: i32,
+    pub max_depth: i32,
+    // Bounces after which paths are ended at random by Russian roulette,
+    // None to always trace them up to `max_depth`.
+    pub roulette_depth: Option<i32>,
+    pub integrator: IntegratorKind,
+    pub seed: u64,
+}
+
+impl Default for RenderSettings {
+    fn default() -> RenderSettings {
+        RenderSettings {
+            image_width: 1920,
+            image_height: 1200,
+            samples_per_pixel_x: 16,
+            samples_per_pixel_y: 16,
+            max_depth: 50,
+            roulette_depth: None,
+            integrator: IntegratorKind::Path,
+            seed: 675248,
+        }
+    }
+}
+
+impl RenderSettings {
+    pub fn aspect_ratio(&self) -> f64 {
+        return self.image_width as f64 / self.image_height as f64;
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.image_width < 2 || self.image_height < 2 {
+            return Err(format!(
+                "the image must be at least 2x2 pixels, got {}x{}",
+                self.image_width, self.image_height
+            ));
+        }
+        // Pixels and samples are counted in i32.
+        if self.image_width.checked_mul(self.image_height).is_none() {
+            return Err(format!(
+                "the image is too large, got {}x{} pixels",
+                self.image_width, self.image_height
+            ));
+        }
+        if self.samples_per_pixel_x < 1 || self.samples_per_pixel_y < 1 {
+            return Err(String::from("at least one sample per pixel is required"));
+        }
+        if self
+            .samples_per_pixel_x
+            .checked_mul(self.samples_per_pixel_y)
+            .is_none()
+        {
+            return Err(format!(
+                "too many samples per pixel, got {}x{}",
+                self.samples_per_pixel_x, self.samples_per_pixel_y
+            ));
+        }
+        if self.max_depth < 1 {
+            return Err(String::from("the maximum depth must be at least 1"));
+        }
+        if self.roulette_depth.is_some_and(|depth| depth < 0) {
+            return Err(String::from(
+                "the Russian roulette depth must not be negative",
+            ));
+        }
+        return Ok(());
+    }
+}
+
+pub struct Scene {
+    pub world: HittableList,
+    // Emissive shapes that are also sampled directly, a subset of `world`.
+    pub lights: HittableList,
+    // Point, spot and directional lights, which only light the scene through
+    // shadow rays.
+    pub delta_lights: Vec<Arc<dyn DeltaLight>>,
+    pub camera: CameraSettings,
+    pub settings: RenderSettings,
+    pub background: Background,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn camera_settings_out_of_range_are_rejected() {
+        assert!(CameraSettings::default().validate().is_ok());
+        for camera in [
+            CameraSettings {
+                vfov: f64::NAN,
+                ..Default::default()
+            },
+            CameraSettings {
+                aperture: -0.1,
+                ..Default::default()
+            },
+            CameraSettings {
+                focus_dist: 0.0,
+                ..Default::default()
+            },
+        ] {
+            assert!(camera.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn counts_that_overflow_are_rejected() {
+        assert!(RenderSettings::default().validate().is_ok());
+
+        let settings = RenderSettings {
+            image_width: 100000,
+            image_height: 100000,
+            ..Default::default()
+        };
+        assert!(settings.validate().unwrap_err().contains("too large"));
+
+        let settings = RenderSettings {
+            samples_per_pixel_x: 100000,
+            samples_per_pixel_y: 100000,
+            ..Default::default()
+        };
+        assert!(settings
+            .validate()
+            .unwrap_err()
+            .contains("too many samples"));
+    }
+}
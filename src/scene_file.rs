@@ -0,0 +1,1447 @@
+use super::aarect::*;
+use super::background::*;
+use super::bvh::*;
+use super::conductor::*;
+use super::constant_medium::*;
+use super::cuboid::*;
+use super::delta_light::*;
+use super::dielectric::*;
+use super::diffuse_light::*;
+use super::environment_map::*;
+use super::hittable::*;
+use super::hittable_list::*;
+use super::instance::*;
+use super::integrator::*;
+use super::isotropic::*;
+use super::lambertian::*;
+use super::material::*;
+use super::mesh::*;
+use super::metal::*;
+use super::moving::*;
+use super::moving_sphere::*;
+use super::obj_loader::*;
+use super::perlin::*;
+use super::quad::*;
+use super::rng::*;
+use super::rough_dielectric::*;
+use super::scene::*;
+use super::sky::*;
+use super::sphere::*;
+use super::texture::*;
+use super::vec3::*;
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+use toml_edit::{ImDocument, Item, TableLike, Value};
+
+// Scene description files are TOML documents:
+//
+//   background = "gradient" | [r, g, b] | { file = "<equirectangular
+//                               .hdr, .exr, .png or .ppm image>", rotation =
+//                               <degrees about y>, intensity } | { type =
+//                               "sky", sun_elevation, sun_azimuth, turbidity,
+//                               intensity }
+//   [camera]                    lookfrom, lookat, vup, vfov, aperture, focus_dist,
+//                               shutter_open, shutter_close
+//   [render]                    width, height, samples_per_pixel or samples_x and
+//                               samples_y, max_depth, roulette_depth, seed,
+//                               integrator = "path" | "simple"
+//   [textures.<name>]           type = "solid" | "checker" | "image" | "noise"
+//                               plus the parameters of that texture
+//   [materials.<name>]          type = "lambertian" | "metal" | "conductor" |
+//                               "dielectric" | "rough_dielectric" | "isotropic" |
+//                               "diffuse_light"
+//                               plus the parameters of that material, where an
+//                               albedo is a color [r, g, b] or "<texture name>"
+//   [[objects]]                 type = "sphere" | "moving_sphere" | "xy_rect" |
+//                               "xz_rect" | "yz_rect" | "quad" | "box"
+//                               plus its parameters and material = "<name>"
+//   [[objects]]                 type = "mesh", file = "<path to an OBJ file>",
+//                               material = "<name>" for faces without usemtl
+//                               and materials = { <usemtl name> = "<name>" }
+//                               Any object can be placed with transform =
+//                               { scale, rotate, translate } or an array of
+//                               them, and move while the shutter is open with
+//                               motion = { displacement, rotate, scale,
+//                               pivot, time0, time1 }. With density = <number> the
+//                               shape bounds a volume of fog or smoke, usually
+//                               with an isotropic material
+//                               Emissive shapes that are not transformed,
+//                               moving or volumes are sampled directly
+//   [[lights]]                  type = "point", position, intensity |
+//                               type = "spot", position, direction,
+//                               intensity, angle, edge | type =
+//                               "directional", direction, irradiance
+//
+// Every error names the file, the line and the key it was found at.
+pub fn load_scene(path: &str) -> Result<Scene, String> {
+    let text = fs::read_to_string(path).map_err(|error| format!("{}: {}", path, error))?;
+    return read_scene(path, &text);
+}
+
+// Builds the scene described by `text`, the contents of the file at `path`.
+fn read_scene(path: &str, text: &str) -> Result<Scene, String> {
+    let document = ImDocument::parse(text).map_err(|error| {
+        let line = line_number(text, error.span().map_or(0, |span| span.start));
+        let message: Vec<&str> = error.message().lines().collect();
+        format!("{}:{}: {}", path, line, message.join(", "))
+    })?;
+
+    let reader = SceneReader { path, text };
+    let root = Section {
+        reader: &reader,
+        table: document.as_table(),
+        name: String::new(),
+        span: None,
+    };
+    root.allow_keys(&[
+        "background",
+        "camera",
+        "render",
+        "textures",
+        "materials",
+        "objects",
+        "lights",
+    ])?;
+
+    let mut scene = Scene {
+        world: HittableList { objects: vec![] },
+        lights: HittableList { objects: vec![] },
+        delta_lights: vec![],
+        camera: CameraSettings::default(),
+        settings: RenderSettings::default(),
+        background: Background::Gradient,
+    };
+
+    if let Some(background) = read_background(&root, path)? {
+        scene.background = background;
+    }
+
+    if let Some(camera) = root.section("camera")? {
+        read_camera(&camera, &mut scene.camera)?;
+    }
+    if let Some(render) = root.section("render")? {
+        read_render(&render, &mut scene.settings)?;
+    }
+
+    // Textures may refer to the ones defined before them.
+    let mut textures: HashMap<String, Arc<dyn Texture>> = HashMap::new();
+    if let Some(section) = root.section("textures")? {
+        for name in section.keys() {
+            let texture = section.section(&name)?.unwrap();
+            let texture = read_texture(&texture, &textures, path)?;
+            textures.insert(name, texture);
+        }
+    }
+
+    let mut materials: HashMap<String, Arc<dyn Material>> = HashMap::new();
+    if let Some(section) = root.section("materials")? {
+        for name in section.keys() {
+            let material = section.section(&name)?.unwrap();
+            materials.insert(name, read_material(&material, &textures)?);
+        }
+    }
+
+    for object in root.sections("objects")? {
+        let mut objects = HittableList { objects: vec![] };
+        if object.string("type")?.as_deref() == Some("mesh") {
+            read_mesh(&object, &materials, path, &mut objects)?;
+        } else {
+            let shape = read_object(&object, &materials)?;
+            if is_sampled_light(&object, &materials)? {
+                scene.lights.add(shape.clone());
+            }
+            objects.add(shape);
+        }
+
+        // A transformed or moving mesh becomes one instance of its own BVH
+        // rather than a wrapper around every triangle.
+        if let Some(transform) = read_transform(&object)? {
+            let instance = Instance::create(combine(objects), transform).ok_or(object.error(
+                Some("transform"),
+                String::from("the transform is singular, check for a scale of 0"),
+            ))?;
+            objects = HittableList {
+                objects: vec![Arc::new(instance)],
+            };
+        }
+        // With a density the shape is the boundary of a volume scattering
+        // by its (isotropic) material.
+        if let Some(density) = object.positive_number("density")? {
+            let material = object.material("material", &materials)?;
+            let medium = ConstantMedium::create(combine(objects), density, material);
+            objects = HittableList {
+                objects: vec![Arc::new(medium)],
+            };
+        }
+        if let Some((motion, time0, time1)) = read_motion(&object)? {
+            objects = HittableList {
+                objects: vec![Arc::new(Moving {
+                    object: combine(objects),
+                    motion,
+                    time0,
+                    time1,
+                })],
+            };
+        }
+        scene.world.objects.extend(objects.objects);
+    }
+
+    for light in root.sections("lights")? {
+        scene.delta_lights.push(read_delta_light(&light)?);
+    }
+
+    return Ok(scene);
+}
+
+fn read_camera(section: &Section, camera: &mut CameraSettings) -> Result<(), String> {
+    section.allow_keys(&[
+        "lookfrom",
+        "lookat",
+        "vup",
+        "vfov",
+        "aperture",
+        "focus_dist",
+        "shutter_open",
+        "shutter_close",
+    ])?;
+
+    camera.lookfrom = section.vec3("lookfrom")?.unwrap_or(camera.lookfrom);
+    camera.lookat = section.vec3("lookat")?.unwrap_or(camera.lookat);
+    camera.vup = section.vec3("vup")?.unwrap_or(camera.vup);
+    if let Some(vfov) = section.number("vfov")? {
+        if !(vfov > 0.0 && vfov < 180.0) {
+            return Err(section.error(
+                Some("vfov"),
+                String::from("expected a number of degrees between 0 and 180"),
+            ));
+        }
+        camera.vfov = vfov;
+    }
+    camera.aperture = section
+        .non_negative_number("aperture")?
+        .unwrap_or(camera.aperture);
+    camera.focus_dist = section
+        .positive_number("focus_dist")?
+        .unwrap_or(camera.focus_dist);
+    camera.shutter_open = section
+        .number("shutter_open")?
+        .unwrap_or(camera.shutter_open);
+    camera.shutter_close = section
+        .number("shutter_close")?
+        .unwrap_or(camera.shutter_close);
+
+    // The camera is checked once the command line has overridden it.
+    return Ok(());
+}
+
+fn read_render(section: &Section, settings: &mut RenderSettings) -> Result<(), String> {
+    section.allow_keys(&[
+        "width",
+        "height",
+        "samples_per_pixel",
+        "samples_x",
+        "samples_y",
+        "max_depth",
+        "roulette_depth",
+        "integrator",
+        "seed",
+    ])?;
+
+    settings.image_width = section
+        .positive_integer("width")?
+        .unwrap_or(settings.image_width);
+    settings.image_height = section
+        .positive_integer("height")?
+        .unwrap_or(settings.image_height);
+    if let Some(samples) = section.positive_integer("samples_per_pixel")? {
+        let side = (samples as f64).sqrt().round() as i32;
+        if side.checked_mul(side) != Some(samples) {
+            return Err(section.error(
+                Some("samples_per_pixel"),
+                String::from(
+                    "expected a square number, use samples_x and samples_y for other grids",
+                ),
+            ));
+        }
+        settings.samples_per_pixel_x = side;
+        settings.samples_per_pixel_y = side;
+    }
+    settings.samples_per_pixel_x = section
+        .positive_integer("samples_x")?
+        .unwrap_or(settings.samples_per_pixel_x);
+    settings.samples_per_pixel_y = section
+        .positive_integer("samples_y")?
+        .unwrap_or(settings.samples_per_pixel_y);
+    settings.max_depth = section
+        .positive_integer("max_depth")?
+        .unwrap_or(settings.max_depth);
+    if let Some(depth) = section.non_negative_integer("roulette_depth")? {
+        settings.roulette_depth = Some(depth);
+    }
+    settings.seed = section.seed("seed")?.unwrap_or(settings.seed);
+    if let Some(name) = section.string("integrator")? {
+        settings.integrator = IntegratorKind::from_name(&name).ok_or(section.error(
+            Some("integrator"),
+            format!("unknown integrator '{}', expected path or simple", name),
+        ))?;
+    }
+
+    // The settings are checked once the command line has overridden them.
+    return Ok(());
+}
+
+fn read_background(section: &Section, scene_path: &str) -> Result<Option<Background>, String> {
+    if section
+        .table
+        .get("background")
+        .is_some_and(|item| item.is_table_like())
+    {
+        let map = section.section("background")?.unwrap();
+        let intensity = map.number("intensity")?.unwrap_or(1.0);
+        if intensity < 0.0 {
+            return Err(map.error(
+                Some("intensity"),
+                String::from("expected a non-negative number"),
+            ));
+        }
+
+        let kind = map.string("type")?.unwrap_or(String::from("map"));
+        if kind == "sky" {
+            map.allow_keys(&[
+                "type",
+                "sun_elevation",
+                "sun_azimuth",
+                "turbidity",
+                "intensity",
+            ])?;
+            let elevation = map.number("sun_elevation")?.unwrap_or(45.0);
+            if !(0.0..=90.0).contains(&elevation) {
+                return Err(map.error(
+                    Some("sun_elevation"),
+                    String::from("expected an angle between 0 and 90 degrees"),
+                ));
+            }
+            let turbidity = map.number("turbidity")?.unwrap_or(3.0);
+            if !(1.7..=10.0).contains(&turbidity) {
+                return Err(map.error(
+                    Some("turbidity"),
+                    String::from("expected a number between 1.7 and 10"),
+                ));
+            }
+            let azimuth = map.number("sun_azimuth")?.unwrap_or(0.0);
+            return Ok(Some(Background::Sky(Sky::create(
+                elevation, azimuth, turbidity, intensity,
+            ))));
+        }
+        if kind != "map" {
+            return Err(map.error(
+                Some("type"),
+                format!("unknown background type '{}', expected map or sky", kind),
+            ));
+        }
+
+        map.allow_keys(&["type", "file", "rotation", "intensity"])?;
+        let file = map.required_string("file")?;
+        let file_path = Path::new(scene_path)
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(file);
+        let environment = EnvironmentMap::load(
+            &file_path.to_string_lossy(),
+            map.number("rotation")?.unwrap_or(0.0),
+            intensity,
+        )
+        .map_err(|message| map.error(Some("file"), message))?;
+        return Ok(Some(Background::Map(Arc::new(environment))));
+    }
+
+    return match section.value("background")? {
+        None => Ok(None),
+        Some(Value::String(name)) if name.value() == "gradient" => Ok(Some(Background::Gradient)),
+        Some(Value::Array(_)) => Ok(section.vec3("background")?.map(Background::Solid)),
+        Some(_) => Err(section.error(
+            Some("background"),
+            String::from(
+                "expected \"gradient\", a color like [0.0, 0.0, 0.0] or an environment map like { file = \"sky.hdr\" }",
+            ),
+        )),
+    };
+}
+
+fn read_texture(
+    section: &Section,
+    textures: &HashMap<String, Arc<dyn Texture>>,
+    scene_path: &str,
+) -> Result<Arc<dyn Texture>, String> {
+    let kind = section.required_string("type")?;
+    return match kind.as_str() {
+        "solid" => {
+            section.allow_keys(&["type", "color"])?;
+            Ok(Arc::new(SolidColor {
+                color: section.required_vec3("color")?,
+            }))
+        }
+        "checker" => {
+            section.allow_keys(&["type", "scale", "even", "odd"])?;
+            let scale = section.positive_number("scale")?.unwrap_or(1.0);
+            Ok(Arc::new(Checker {
+                scale,
+                even: section.texture("even", textures)?,
+                odd: section.texture("odd", textures)?,
+            }))
+        }
+        "image" => {
+            section.allow_keys(&["type", "file"])?;
+            let file = section.required_string("file")?;
+            let file_path = Path::new(scene_path)
+                .parent()
+                .unwrap_or(Path::new(""))
+                .join(file);
+            let texture = ImageTexture::load(&file_path.to_string_lossy())
+                .map_err(|message| section.error(Some("file"), message))?;
+            Ok(Arc::new(texture))
+        }
+        "noise" => {
+            section.allow_keys(&["type", "pattern", "scale", "octaves", "low", "high", "seed"])?;
+            let pattern = match section.string("pattern")? {
+                None => NoisePattern::Noise,
+                Some(name) => NoisePattern::from_name(&name).ok_or(section.error(
+                    Some("pattern"),
+                    format!(
+                        "unknown noise pattern '{}', expected noise, turbulence, fbm, marble or wood",
+                        name
+                    ),
+                ))?,
+            };
+            let octaves = section.positive_integer("octaves")?.unwrap_or(7);
+            // The permutation tables only depend on the texture's seed, not
+            // on the render settings.
+            let seed = section.seed("seed")?.unwrap_or(0);
+            let mut rng = RandomNumberGenerator::create(seed, RngAlgorithm::Pcg32);
+            Ok(Arc::new(NoiseTexture {
+                noise: Arc::new(Perlin::create(&mut rng)),
+                pattern,
+                scale: section.number("scale")?.unwrap_or(1.0),
+                octaves,
+                low: section.vec3("low")?.unwrap_or(Color {
+                    x: 0.0,
+                    y: 0.0,
+                    z: 0.0,
+                }),
+                high: section.vec3("high")?.unwrap_or(Color {
+                    x: 1.0,
+                    y: 1.0,
+                    z: 1.0,
+                }),
+            }))
+        }
+        _ => Err(section.error(
+            Some("type"),
+            format!(
+                "unknown texture type '{}', expected solid, checker, image or noise",
+                kind
+            ),
+        )),
+    };
+}
+
+fn read_material(
+    section: &Section,
+    textures: &HashMap<String, Arc<dyn Texture>>,
+) -> Result<Arc<dyn Material>, String> {
+    let kind = section.required_string("type")?;
+    return match kind.as_str() {
+        "lambertian" => {
+            section.allow_keys(&["type", "albedo"])?;
+            Ok(Arc::new(Lambertian {
+                albedo: section.texture("albedo", textures)?,
+            }))
+        }
+        "metal" => {
+            section.allow_keys(&["type", "albedo", "fuzz"])?;
+            Ok(Arc::new(Metal {
+                albedo: section.texture("albedo", textures)?,
+                fuzz: section.non_negative_number("fuzz")?.unwrap_or(0.0).min(1.0),
+            }))
+        }
+        "conductor" => {
+            section.allow_keys(&["type", "metal", "eta", "k", "roughness"])?;
+            let (eta, k) = match section.string("metal")? {
+                Some(name) => {
+                    if section.value("eta")?.is_some() || section.value("k")?.is_some() {
+                        return Err(section.error(
+                            Some("metal"),
+                            String::from("a metal preset cannot be combined with eta and k"),
+                        ));
+                    }
+                    Conductor::preset(&name).ok_or(section.error(
+                        Some("metal"),
+                        format!(
+                            "unknown metal '{}', expected gold, copper, aluminium or silver",
+                            name
+                        ),
+                    ))?
+                }
+                None => (section.required_vec3("eta")?, section.required_vec3("k")?),
+            };
+            let roughness = section.number("roughness")?.unwrap_or(0.0);
+            if !(0.0..=1.0).contains(&roughness) {
+                return Err(section.error(
+                    Some("roughness"),
+                    String::from("expected a number between 0 and 1"),
+                ));
+            }
+            Ok(Arc::new(Conductor::create(eta, k, roughness)))
+        }
+        "dielectric" => {
+            section.allow_keys(&["type", "ir", "absorption", "transmittance", "distance"])?;
+            Ok(Arc::new(Dielectric {
+                ir: section.required_positive_number("ir")?,
+                absorption: read_absorption(section)?,
+            }))
+        }
+        "rough_dielectric" => {
+            section.allow_keys(&[
+                "type", "ir", "roughness", "absorption", "transmittance", "distance",
+            ])?;
+            let roughness = section.number("roughness")?.unwrap_or(0.0);
+            if !(0.0..=1.0).contains(&roughness) {
+                return Err(section.error(
+                    Some("roughness"),
+                    String::from("expected a number between 0 and 1"),
+                ));
+            }
+            Ok(Arc::new(RoughDielectric::create(
+                section.required_positive_number("ir")?,
+                roughness,
+                read_absorption(section)?,
+            )))
+        }
+        "isotropic" => {
+            section.allow_keys(&["type", "albedo"])?;
+            Ok(Arc::new(Isotropic {
+                albedo: section.texture("albedo", textures)?,
+            }))
+        }
+        "diffuse_light" => {
+            section.allow_keys(&["type", "emit"])?;
+            Ok(Arc::new(DiffuseLight {
+                emit: section.required_vec3("emit")?,
+            }))
+        }
+        _ => Err(section.error(
+            Some("type"),
+            format!(
+                "unknown material type '{}', expected lambertian, metal, conductor, dielectric, rough_dielectric, isotropic or diffuse_light",
+                kind
+            ),
+        )),
+    };
+}
+
+// The absorption coefficient of a dielectric, either given directly or as
+// the colour white light takes after `distance` inside. Clear by default.
+fn read_absorption(section: &Section) -> Result<Color, String> {
+    if let Some(absorption) = section.vec3("absorption")? {
+        if section.value("transmittance")?.is_some() || section.value("distance")?.is_some() {
+            return Err(section.error(
+                Some("absorption"),
+                String::from("absorption cannot be combined with transmittance and distance"),
+            ));
+        }
+        if absorption.x < 0.0 || absorption.y < 0.0 || absorption.z < 0.0 {
+            return Err(section.error(
+                Some("absorption"),
+                String::from("expected non-negative components"),
+            ));
+        }
+        return Ok(absorption);
+    }
+
+    let transmittance = match section.vec3("transmittance")? {
+        Some(transmittance) => transmittance,
+        None => {
+            if section.value("distance")?.is_some() {
+                return Err(section.error(
+                    Some("distance"),
+                    String::from("distance needs a transmittance"),
+                ));
+            }
+            return Ok(Color {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+            });
+        }
+    };
+    let in_range = |c: f64| c > 0.0 && c <= 1.0;
+    if !in_range(transmittance.x) || !in_range(transmittance.y) || !in_range(transmittance.z) {
+        return Err(section.error(
+            Some("transmittance"),
+            String::from("expected components greater than 0 and at most 1"),
+        ));
+    }
+    let distance = section.positive_number("distance")?.unwrap_or(1.0);
+    return Ok(Color {
+        x: -transmittance.x.ln() / distance,
+        y: -transmittance.y.ln() / distance,
+        z: -transmittance.z.ln() / distance,
+    });
+}
+
+fn read_object(
+    section: &Section,
+    materials: &HashMap<String, Arc<dyn Material>>,
+) -> Result<Arc<dyn Hittable>, String> {
+    let kind = section.required_string("type")?;
+    return match kind.as_str() {
+        "sphere" => {
+            section.allow_keys(&["type", "center", "radius", "material", "motion", "transform", "density"])?;
+            Ok(Arc::new(Sphere {
+                center: section.required_vec3("center")?,
+                radius: section.required_positive_number("radius")?,
+                material: section.material("material", materials)?,
+            }))
+        }
+        "moving_sphere" => {
+            section.allow_keys(&[
+                "type", "center0", "center1", "time0", "time1", "radius", "material", "motion",
+                "transform", "density",
+            ])?;
+            let (time0, time1) = read_time_interval(section)?;
+            Ok(Arc::new(MovingSphere {
+                center0: section.required_vec3("center0")?,
+                center1: section.required_vec3("center1")?,
+                time0,
+                time1,
+                radius: section.required_positive_number("radius")?,
+                material: section.material("material", materials)?,
+            }))
+        }
+        "xy_rect" => {
+            section.allow_keys(&["type", "x0", "x1", "y0", "y1", "k", "material", "motion", "transform", "density"])?;
+            let (x0, x1) = read_extent(section, "x0", "x1")?;
+            let (y0, y1) = read_extent(section, "y0", "y1")?;
+            Ok(Arc::new(XyRect {
+                x0,
+                x1,
+                y0,
+                y1,
+                k: section.required_number("k")?,
+                material: section.material("material", materials)?,
+            }))
+        }
+        "xz_rect" => {
+            section.allow_keys(&["type", "x0", "x1", "z0", "z1", "k", "material", "motion", "transform", "density"])?;
+            let (x0, x1) = read_extent(section, "x0", "x1")?;
+            let (z0, z1) = read_extent(section, "z0", "z1")?;
+            Ok(Arc::new(XzRect {
+                x0,
+                x1,
+                z0,
+                z1,
+                k: section.required_number("k")?,
+                material: section.material("material", materials)?,
+            }))
+        }
+        "yz_rect" => {
+            section.allow_keys(&["type", "y0", "y1", "z0", "z1", "k", "material", "motion", "transform", "density"])?;
+            let (y0, y1) = read_extent(section, "y0", "y1")?;
+            let (z0, z1) = read_extent(section, "z0", "z1")?;
+            Ok(Arc::new(YzRect {
+                y0,
+                y1,
+                z0,
+                z1,
+                k: section.required_number("k")?,
+                material: section.material("material", materials)?,
+            }))
+        }
+        "quad" => {
+            section.allow_keys(&["type", "q", "u", "v", "material", "motion", "transform", "density"])?;
+            let q = section.required_vec3("q")?;
+            let u = section.required_vec3("u")?;
+            let v = section.required_vec3("v")?;
+            if near_zero(cross(u, v)) {
+                return Err(section.error(Some("v"), String::from("the edges u and v must not be parallel")));
+            }
+            Ok(Arc::new(Quad::create(q, u, v, section.material("material", materials)?)))
+        }
+        "box" => {
+            section.allow_keys(&["type", "min", "max", "material", "motion", "transform", "density"])?;
+            let min = section.required_vec3("min")?;
+            let max = section.required_vec3("max")?;
+            if min.x == max.x || min.y == max.y || min.z == max.z {
+                return Err(section.error(
+                    Some("max"),
+                    String::from("min and max must differ on every axis for the box to have a volume"),
+                ));
+            }
+            Ok(Arc::new(Cuboid::create(
+                min,
+                max,
+                section.material("material", materials)?,
+            )))
+        }
+        _ => Err(section.error(
+            Some("type"),
+            format!(
+                "unknown object type '{}', expected sphere, moving_sphere, xy_rect, xz_rect, yz_rect, quad, box or mesh",
+                kind
+            ),
+        )),
+    };
+}
+
+// The two bounds of a rectangle along one axis, lowest first, as the
+// rectangles expect. They must differ for the rectangle to have an area.
+fn read_extent(section: &Section, key0: &str, key1: &str) -> Result<(f64, f64), String> {
+    let bound0 = section.required_number(key0)?;
+    let bound1 = section.required_number(key1)?;
+    if bound0 == bound1 {
+        return Err(section.error(
+            Some(key1),
+            format!(
+                "must differ from {} for the rectangle to have an area",
+                key0
+            ),
+        ));
+    }
+    return Ok((bound0.min(bound1), bound0.max(bound1)));
+}
+
+fn read_delta_light(section: &Section) -> Result<Arc<dyn DeltaLight>, String> {
+    let kind = section.required_string("type")?;
+    return match kind.as_str() {
+        "point" => {
+            section.allow_keys(&["type", "position", "intensity"])?;
+            Ok(Arc::new(PointLight {
+                position: section.required_vec3("position")?,
+                intensity: section.required_vec3("intensity")?,
+            }))
+        }
+        "spot" => {
+            section.allow_keys(&[
+                "type",
+                "position",
+                "direction",
+                "intensity",
+                "angle",
+                "edge",
+            ])?;
+            let direction = section.required_vec3("direction")?;
+            if near_zero(direction) {
+                return Err(section.error(
+                    Some("direction"),
+                    String::from("the direction must not be zero"),
+                ));
+            }
+            let angle = section.number("angle")?.unwrap_or(30.0);
+            if angle <= 0.0 || angle > 90.0 {
+                return Err(section.error(
+                    Some("angle"),
+                    String::from("expected a half-angle between 0 and 90 degrees"),
+                ));
+            }
+            let edge = section.number("edge")?.unwrap_or(5.0);
+            if edge < 0.0 {
+                return Err(
+                    section.error(Some("edge"), String::from("expected a non-negative number"))
+                );
+            }
+            Ok(Arc::new(SpotLight::create(
+                section.required_vec3("position")?,
+                direction,
+                section.required_vec3("intensity")?,
+                angle,
+                edge,
+            )))
+        }
+        "directional" => {
+            section.allow_keys(&["type", "direction", "irradiance"])?;
+            let direction = section.required_vec3("direction")?;
+            if near_zero(direction) {
+                return Err(section.error(
+                    Some("direction"),
+                    String::from("the direction must not be zero"),
+                ));
+            }
+            Ok(Arc::new(DirectionalLight {
+                direction,
+                irradiance: section.required_vec3("irradiance")?,
+            }))
+        }
+        _ => Err(section.error(
+            Some("type"),
+            format!(
+                "unknown light type '{}', expected point, spot or directional",
+                kind
+            ),
+        )),
+    };
+}
+
+// Emissive spheres, rectangles, quads and boxes are sampled directly. Lights
+// that are transformed, moving or bound a volume are only found by the rays
+// that scatter into them.
+fn is_sampled_light(
+    section: &Section,
+    materials: &HashMap<String, Arc<dyn Material>>,
+) -> Result<bool, String> {
+    let kind = section.required_string("type")?;
+    if !["sphere", "xy_rect", "xz_rect", "yz_rect", "quad", "box"].contains(&kind.as_str()) {
+        return Ok(false);
+    }
+    for key in ["transform", "motion", "density"] {
+        if section.value(key)?.is_some() {
+            return Ok(false);
+        }
+    }
+    return Ok(section.material("material", materials)?.is_emissive());
+}
+
+fn combine(mut objects: HittableList) -> Arc<dyn Hittable> {
+    if objects.objects.len() == 1 {
+        return objects.objects.pop().unwrap();
+    }
+    return build_bvh(objects);
+}
+
+// Reads the optional transform of an object: a table with a scale (a number
+// or [x, y, z]), a rotate = [x, y, z] in degrees about each axis in turn and
+// a translate = [x, y, z], applied in that order. An array of such tables
+// applies them one after the other.
+fn read_transform(section: &Section) -> Result<Option<Mat4>, String> {
+    let steps = match section.table.get("transform") {
+        None => return Ok(None),
+        Some(item) if item.is_array() || item.is_array_of_tables() => {
+            section.sections("transform")?
+        }
+        Some(_) => vec![section.section("transform")?.unwrap()],
+    };
+
+    let mut transform = Mat4::identity();
+    for step in steps.iter() {
+        step.allow_keys(&["scale", "rotate", "translate"])?;
+
+        let scale = match read_scale(step)? {
+            None => Mat4::identity(),
+            Some(factors) => Mat4::scaling(factors),
+        };
+
+        let mut rotation = Mat4::identity();
+        if let Some(angles) = step.vec3("rotate")? {
+            for (axis, degrees) in [(0, angles.x), (1, angles.y), (2, angles.z)] {
+                let mut unit = [0.0; 3];
+                unit[axis] = 1.0;
+                let axis = Vec3 {
+                    x: unit[0],
+                    y: unit[1],
+                    z: unit[2],
+                };
+                rotation = Mat4::rotation(axis, degrees) * rotation;
+            }
+        }
+
+        let translation = match step.vec3("translate")? {
+            None => Mat4::identity(),
+            Some(offset) => Mat4::translation(offset),
+        };
+
+        transform = translation * rotation * scale * transform;
+    }
+    return Ok(Some(transform));
+}
+
+// Reads an optional scale, either one number for all axes or [x, y, z].
+fn read_scale(section: &Section) -> Result<Option<Vec3>, String> {
+    return match section.value("scale")? {
+        None => Ok(None),
+        Some(Value::Array(_)) => Ok(Some(section.required_vec3("scale")?)),
+        Some(_) => {
+            let factor = section.required_number("scale")?;
+            Ok(Some(Vec3 {
+                x: factor,
+                y: factor,
+                z: factor,
+            }))
+        }
+    };
+}
+
+// Reads the optional time0 and time1 of a move, 0 and 1 by default.
+fn read_time_interval(section: &Section) -> Result<(f64, f64), String> {
+    let time0 = section.number("time0")?.unwrap_or(0.0);
+    let time1 = section.number("time1")?.unwrap_or(1.0);
+    if time1 < time0 {
+        return Err(section.error(
+            Some("time1"),
+            String::from("the move must end after it starts"),
+        ));
+    }
+    return Ok((time0, time1));
+}
+
+// Reads the optional motion = { displacement = [x, y, z], rotate = [x, y,
+// z], scale, pivot = [x, y, z], time0, time1 } of an object. The rotation is
+// in degrees about each axis in turn and, like the scale, about the pivot.
+fn read_motion(section: &Section) -> Result<Option<(Motion, f64, f64)>, String> {
+    let motion = match section.section("motion")? {
+        None => return Ok(None),
+        Some(motion) => motion,
+    };
+    motion.allow_keys(&["displacement", "rotate", "scale", "pivot", "time0", "time1"])?;
+    let zero = Vec3 {
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+    };
+
+    let mut rotation = Quaternion::identity();
+    if let Some(angles) = motion.vec3("rotate")? {
+        for (axis, degrees) in [(0, angles.x), (1, angles.y), (2, angles.z)] {
+            let mut unit = [0.0; 3];
+            unit[axis] = 1.0;
+            let axis = Vec3 {
+                x: unit[0],
+                y: unit[1],
+                z: unit[2],
+            };
+            rotation = Quaternion::rotation(axis, degrees) * rotation;
+        }
+    }
+    let scale = read_scale(&motion)?.unwrap_or(Vec3 {
+        x: 1.0,
+        y: 1.0,
+        z: 1.0,
+    });
+    if scale.x <= 0.0 || scale.y <= 0.0 || scale.z <= 0.0 {
+        return Err(motion.error(Some("scale"), String::from("expected a positive scale")));
+    }
+
+    let (time0, time1) = read_time_interval(&motion)?;
+    return Ok(Some((
+        Motion {
+            displacement: motion.vec3("displacement")?.unwrap_or(zero),
+            rotation,
+            scale,
+            pivot: motion.vec3("pivot")?.unwrap_or(zero),
+        },
+        time0,
+        time1,
+    )));
+}
+
+// Loads the triangles of an OBJ file, relative to the scene file. The faces
+// of each usemtl group get the scene material that `materials` maps the
+// group's name to, or else the scene material of the same name, or else
+// `material`.
+fn read_mesh(
+    section: &Section,
+    materials: &HashMap<String, Arc<dyn Material>>,
+    scene_path: &str,
+    world: &mut HittableList,
+) -> Result<(), String> {
+    section.allow_keys(&[
+        "type",
+        "file",
+        "material",
+        "materials",
+        "motion",
+        "transform",
+        "density",
+    ])?;
+
+    let file = section.required_string("file")?;
+    let file_path = Path::new(scene_path)
+        .parent()
+        .unwrap_or(Path::new(""))
+        .join(&file);
+    let model = load_obj(&file_path.to_string_lossy())?;
+
+    let default_material = match section.value("material")? {
+        Some(_) => Some(section.material("material", materials)?),
+        None => None,
+    };
+    let mut group_materials: HashMap<String, Arc<dyn Material>> = HashMap::new();
+    if let Some(mapping) = section.section("materials")? {
+        for name in mapping.keys() {
+            group_materials.insert(name.clone(), mapping.material(&name, materials)?);
+        }
+    }
+
+    for group in model.groups.iter() {
+        let material = match &group.material {
+            Some(name) => group_materials
+                .get(name)
+                .or(materials.get(name))
+                .cloned()
+                .or(default_material.clone()),
+            None => default_material.clone(),
+        };
+        let material = match (material, &group.material) {
+            (Some(material), _) => material,
+            (None, Some(name)) => {
+                return Err(section.error(
+                    Some("materials"),
+                    format!("no material for usemtl '{}' in {}", name, file),
+                ))
+            }
+            (None, None) => {
+                return Err(section.error(
+                    Some("material"),
+                    format!(
+                        "{} has faces without usemtl, a default material is required",
+                        file
+                    ),
+                ))
+            }
+        };
+        add_mesh(world, model.mesh.clone(), &group.faces, material);
+    }
+    return Ok(());
+}
+
+fn line_number(text: &str, offset: usize) -> usize {
+    let offset = offset.min(text.len());
+    return text[..offset].matches('\n').count() + 1;
+}
+
+struct SceneReader<'a> {
+    path: &'a str,
+    text: &'a str,
+}
+
+// A table of the document together with its dotted name, used to point
+// errors at the offending key.
+struct Section<'a> {
+    reader: &'a SceneReader<'a>,
+    table: &'a dyn TableLike,
+    name: String,
+    span: Option<Range<usize>>,
+}
+
+impl<'a> Section<'a> {
+    fn key_name(&self, key: &str) -> String {
+        if self.name.is_empty() {
+            return String::from(key);
+        }
+        return format!("{}.{}", self.name, key);
+    }
+
+    fn key_span(&self, key: &str) -> Option<Range<usize>> {
+        return self.table.key(key).and_then(|key| key.span());
+    }
+
+    // Formats an error for `key`, or for the whole section when there is none.
+    fn error(&self, key: Option<&str>, message: String) -> String {
+        let (span, name) = match key {
+            Some(key) => (self.key_span(key).or(self.span.clone()), self.key_name(key)),
+            None => (self.span.clone(), self.name.clone()),
+        };
+        let line = line_number(self.reader.text, span.map_or(0, |span| span.start));
+        if name.is_empty() {
+            return format!("{}:{}: {}", self.reader.path, line, message);
+        }
+        return format!("{}:{}: {}: {}", self.reader.path, line, name, message);
+    }
+
+    fn keys(&self) -> Vec<String> {
+        return self
+            .table
+            .iter()
+            .map(|(key, _)| String::from(key))
+            .collect();
+    }
+
+    fn allow_keys(&self, allowed: &[&str]) -> Result<(), String> {
+        for (key, _) in self.table.iter() {
+            if !allowed.contains(&key) {
+                return Err(self.error(
+                    Some(key),
+                    format!("unknown key, expected one of {}", allowed.join(", ")),
+                ));
+            }
+        }
+        return Ok(());
+    }
+
+    fn value(&self, key: &str) -> Result<Option<&'a Value>, String> {
+        return match self.table.get(key) {
+            None => Ok(None),
+            Some(Item::Value(value)) => Ok(Some(value)),
+            Some(_) => Err(self.error(Some(key), String::from("expected a value, not a table"))),
+        };
+    }
+
+    fn required<T>(&self, key: &str, value: Option<T>) -> Result<T, String> {
+        return value.ok_or(self.error(None, format!("missing required key '{}'", key)));
+    }
+
+    fn number(&self, key: &str) -> Result<Option<f64>, String> {
+        return match self.value(key)? {
+            None => Ok(None),
+            Some(value) => match value_to_number(value) {
+                Some(number) => Ok(Some(number)),
+                None => Err(self.error(Some(key), String::from("expected a number"))),
+            },
+        };
+    }
+
+    fn required_number(&self, key: &str) -> Result<f64, String> {
+        let number = self.number(key)?;
+        return self.required(key, number);
+    }
+
+    // NaN is neither positive nor non-negative.
+    fn positive_number(&self, key: &str) -> Result<Option<f64>, String> {
+        return match self.number(key)? {
+            None => Ok(None),
+            Some(number) if number > 0.0 => Ok(Some(number)),
+            Some(_) => Err(self.error(Some(key), String::from("expected a positive number"))),
+        };
+    }
+
+    fn required_positive_number(&self, key: &str) -> Result<f64, String> {
+        let number = self.positive_number(key)?;
+        return self.required(key, number);
+    }
+
+    fn non_negative_number(&self, key: &str) -> Result<Option<f64>, String> {
+        return match self.number(key)? {
+            None => Ok(None),
+            Some(number) if number >= 0.0 => Ok(Some(number)),
+            Some(_) => Err(self.error(Some(key), String::from("expected a non-negative number"))),
+        };
+    }
+
+    fn integer(&self, key: &str) -> Result<Option<i64>, String> {
+        return match self.value(key)? {
+            None => Ok(None),
+            Some(Value::Integer(integer)) => Ok(Some(*integer.value())),
+            Some(_) => Err(self.error(Some(key), String::from("expected an integer"))),
+        };
+    }
+
+    fn positive_integer(&self, key: &str) -> Result<Option<i32>, String> {
+        return match self.integer(key)? {
+            None => Ok(None),
+            Some(integer) if integer > 0 && integer <= i32::MAX as i64 => Ok(Some(integer as i32)),
+            Some(_) => Err(self.error(Some(key), String::from("expected a positive integer"))),
+        };
+    }
+
+    fn non_negative_integer(&self, key: &str) -> Result<Option<i32>, String> {
+        return match self.integer(key)?.map(i32::try_from) {
+            None => Ok(None),
+            Some(Ok(integer)) if integer >= 0 => Ok(Some(integer)),
+            Some(_) => Err(self.error(Some(key), String::from("expected a non-negative integer"))),
+        };
+    }
+
+    fn seed(&self, key: &str) -> Result<Option<u64>, String> {
+        return match self.integer(key)?.map(u64::try_from) {
+            None => Ok(None),
+            Some(Ok(seed)) => Ok(Some(seed)),
+            Some(Err(_)) => {
+                Err(self.error(Some(key), String::from("expected a non-negative integer")))
+            }
+        };
+    }
+
+    fn string(&self, key: &str) -> Result<Option<String>, String> {
+        return match self.value(key)? {
+            None => Ok(None),
+            Some(Value::String(string)) => Ok(Some(string.value().clone())),
+            Some(_) => Err(self.error(Some(key), String::from("expected a string"))),
+        };
+    }
+
+    fn required_string(&self, key: &str) -> Result<String, String> {
+        let string = self.string(key)?;
+        return self.required(key, string);
+    }
+
+    fn vec3(&self, key: &str) -> Result<Option<Vec3>, String> {
+        let value = match self.value(key)? {
+            None => return Ok(None),
+            Some(value) => value,
+        };
+        let components: Option<Vec<f64>> = value
+            .as_array()
+            .map(|array| array.iter().map(value_to_number).collect())
+            .unwrap_or(None);
+        return match components.as_deref() {
+            Some([x, y, z]) => Ok(Some(Vec3 {
+                x: *x,
+                y: *y,
+                z: *z,
+            })),
+            _ => Err(self.error(
+                Some(key),
+                String::from("expected an array of three numbers like [1.0, 2.0, 3.0]"),
+            )),
+        };
+    }
+
+    fn required_vec3(&self, key: &str) -> Result<Vec3, String> {
+        let vec3 = self.vec3(key)?;
+        return self.required(key, vec3);
+    }
+
+    fn material(
+        &self,
+        key: &str,
+        materials: &HashMap<String, Arc<dyn Material>>,
+    ) -> Result<Arc<dyn Material>, String> {
+        let name = self.required_string(key)?;
+        return match materials.get(&name) {
+            Some(material) => Ok(material.clone()),
+            None => Err(self.error(
+                Some(key),
+                format!(
+                    "unknown material '{}', define it under [materials.{}]",
+                    name, name
+                ),
+            )),
+        };
+    }
+
+    // A texture given either as a color or by the name of a texture.
+    fn texture(
+        &self,
+        key: &str,
+        textures: &HashMap<String, Arc<dyn Texture>>,
+    ) -> Result<Arc<dyn Texture>, String> {
+        let name = match self.value(key)? {
+            Some(Value::String(name)) => name.value().clone(),
+            _ => {
+                return Ok(Arc::new(SolidColor {
+                    color: self.required_vec3(key)?,
+                }))
+            }
+        };
+        return match textures.get(&name) {
+            Some(texture) => Ok(texture.clone()),
+            None => Err(self.error(
+                Some(key),
+                format!(
+                    "unknown texture '{}', define it under [textures.{}] before using it",
+                    name, name
+                ),
+            )),
+        };
+    }
+
+    fn section(&self, key: &str) -> Result<Option<Section<'a>>, String> {
+        let item = match self.table.get(key) {
+            None => return Ok(None),
+            Some(item) => item,
+        };
+        return match item.as_table_like() {
+            Some(table) => Ok(Some(Section {
+                reader: self.reader,
+                table,
+                name: self.key_name(key),
+                span: item.span().or(self.key_span(key)),
+            })),
+            None => Err(self.error(Some(key), String::from("expected a table"))),
+        };
+    }
+
+    // Reads an array of tables, written either as [[key]] or as an array of
+    // inline tables.
+    fn sections(&self, key: &str) -> Result<Vec<Section<'a>>, String> {
+        let item = match self.table.get(key) {
+            None => return Ok(vec![]),
+            Some(item) => item,
+        };
+
+        if let Some(array) = item.as_array_of_tables() {
+            return Ok(array
+                .iter()
+                .enumerate()
+                .map(|(index, table)| Section {
+                    reader: self.reader,
+                    table: table as &dyn TableLike,
+                    name: format!("{}[{}]", self.key_name(key), index),
+                    span: table.span(),
+                })
+                .collect());
+        }
+
+        if let Some(array) = item.as_array() {
+            let mut sections = vec![];
+            for (index, value) in array.iter().enumerate() {
+                match value.as_inline_table() {
+                    Some(table) => sections.push(Section {
+                        reader: self.reader,
+                        table: table as &dyn TableLike,
+                        name: format!("{}[{}]", self.key_name(key), index),
+                        span: value.span(),
+                    }),
+                    None => {
+                        return Err(
+                            self.error(Some(key), String::from("expected an array of tables"))
+                        )
+                    }
+                }
+            }
+            return Ok(sections);
+        }
+
+        return Err(self.error(Some(key), String::from("expected an array of tables")));
+    }
+}
+
+fn value_to_number(value: &Value) -> Option<f64> {
+    return match value {
+        Value::Float(float) => Some(*float.value()),
+        Value::Integer(integer) => Some(*integer.value() as f64),
+        _ => None,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::hit_record::*;
+    use super::super::ray::*;
+    use super::*;
+
+    fn error_of(text: &str) -> String {
+        return match read_scene("scene.toml", text) {
+            Ok(_) => panic!("expected an error"),
+            Err(message) => message,
+        };
+    }
+
+    const SPHERES: &str = r#"
+[materials.grey]
+type = "lambertian"
+albedo = [0.5, 0.5, 0.5]
+
+[[objects]]
+type = "sphere"
+center = [0.0, 0.0, 0.0]
+radius = 1.0
+material = "grey"
+
+[[objects]]
+type = "sphere"
+center = [0.0, 2.0, 0.0]
+radius = 0.0
+material = "grey"
+"#;
+
+    #[test]
+    fn errors_name_the_file_line_and_key() {
+        assert_eq!(
+            error_of(SPHERES),
+            "scene.toml:15: objects[1].radius: expected a positive number"
+        );
+        assert_eq!(
+            error_of("[camera]\nvfov = 40.0\n\n[render]\nwidth = \"wide\"\n"),
+            "scene.toml:5: render.width: expected an integer"
+        );
+        assert_eq!(
+            error_of("[render]\nseed = -1\n"),
+            "scene.toml:2: render.seed: expected a non-negative integer"
+        );
+        assert_eq!(
+            error_of("[textures.stone]\ntype = \"noise\"\nseed = -3\n"),
+            "scene.toml:3: textures.stone.seed: expected a non-negative integer"
+        );
+        assert!(error_of("[render]\nwidht = 640\n")
+            .starts_with("scene.toml:2: render.widht: unknown key, expected one of width, height"));
+    }
+
+    #[test]
+    fn out_of_range_numbers_are_rejected() {
+        assert_eq!(
+            error_of("[materials.m]\ntype = \"metal\"\nalbedo = [1.0, 1.0, 1.0]\nfuzz = -0.5\n"),
+            "scene.toml:4: materials.m.fuzz: expected a non-negative number"
+        );
+        assert_eq!(
+            error_of("[materials.m]\ntype = \"dielectric\"\nir = 0.0\n"),
+            "scene.toml:3: materials.m.ir: expected a positive number"
+        );
+        assert_eq!(
+            error_of("[materials.m]\ntype = \"rough_dielectric\"\nir = -1.5\n"),
+            "scene.toml:3: materials.m.ir: expected a positive number"
+        );
+        assert_eq!(
+            error_of("[camera]\naperture = -0.1\n"),
+            "scene.toml:2: camera.aperture: expected a non-negative number"
+        );
+        assert_eq!(
+            error_of("[camera]\nfocus_dist = 0.0\n"),
+            "scene.toml:2: camera.focus_dist: expected a positive number"
+        );
+        assert_eq!(
+            error_of("[camera]\nvfov = nan\n"),
+            "scene.toml:2: camera.vfov: expected a number of degrees between 0 and 180"
+        );
+    }
+
+    #[test]
+    fn syntax_errors_name_the_line() {
+        assert!(error_of("[render]\nwidth = 640\nheight =\n").starts_with("scene.toml:3: "));
+    }
+
+    #[test]
+    fn rectangle_bounds_may_be_given_in_any_order() {
+        let text = r#"
+[materials.light]
+type = "diffuse_light"
+emit = [1.0, 1.0, 1.0]
+
+[[objects]]
+type = "xy_rect"
+x0 = 5.0
+x1 = -5.0
+y0 = 0.0
+y1 = 1.0
+k = 0.0
+material = "light"
+"#;
+        let scene = match read_scene("scene.toml", text) {
+            Ok(scene) => scene,
+            Err(message) => panic!("{}", message),
+        };
+        // The light is found, and sampled with a positive density.
+        let mut rng = RandomNumberGenerator::create(1, RngAlgorithm::Pcg32);
+        let origin = Point3 {
+            x: 1.0,
+            y: 0.5,
+            z: 2.0,
+        };
+        let direction = Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: -1.0,
+        };
+        let ray = Ray {
+            origin,
+            direction,
+            time: 0.0,
+        };
+        let mut rec = HitRecord::new();
+        assert!(scene
+            .lights
+            .hit(&mut rng, ray, 0.001, f64::INFINITY, &mut rec));
+        assert!(scene.lights.pdf_value(&mut rng, origin, direction) > 0.0);
+    }
+
+    #[test]
+    fn a_valid_scene_loads() {
+        let text = SPHERES.replace("radius = 0.0", "radius = 0.5");
+        let scene = match read_scene("scene.toml", &text) {
+            Ok(scene) => scene,
+            Err(message) => panic!("{}", message),
+        };
+        assert_eq!(scene.world.objects.len(), 2);
+    }
+}
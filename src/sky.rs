@@ -0,0 +1,210 @@
+use super::onb::*;
+use super::rng::*;
+use super::utils::*;
+use super::vec3::*;
+use std::f64::consts::PI;
+
+// Angular radius of the sun disk, in radians.
+const SUN_ANGULAR_RADIUS: f64 = 0.2665 * PI / 180.0;
+// Luminance of the sun above the atmosphere, in kcd/m² like the sky.
+const SUN_LUMINANCE: f64 = 1.6e6;
+// Luminances in kcd/m² are scaled so that a white surface lit by the sun
+// high in the sky is about 1.
+const LUMINANCE_SCALE: f64 = 1.0 / 25.0;
+// How often a light sample picks the sun rather than the whole sky.
+const SUN_SAMPLING_PROBABILITY: f64 = 0.5;
+
+// Coefficients A to E of the Perez sky luminance distribution.
+#[derive(Copy, Clone)]
+struct Perez {
+    a: f64,
+    b: f64,
+    c: f64,
+    d: f64,
+    e: f64,
+}
+
+impl Perez {
+    // `cos_theta` is the cosine of the angle to the zenith, `gamma` the angle
+    // to the sun.
+    fn value(&self, cos_theta: f64, gamma: f64) -> f64 {
+        return (1.0 + self.a * (self.b / cos_theta).exp())
+            * (1.0 + self.c * (self.d * gamma).exp() + self.e * gamma.cos().powi(2));
+    }
+}
+
+// The analytic daylight model of Preetham, Shirley and Smits ("A Practical
+// Analytic Model for Daylight", 1999) with a sun disk whose colour comes from
+// the same atmosphere. `turbidity` is the haziness of the air, from about 2
+// for a clear sky to 10 for a hazy one. The sun is `elevation` degrees above
+// the horizon and `azimuth` degrees from -z towards +x.
+#[derive(Copy, Clone)]
+pub struct Sky {
+    sun_direction: Vec3,
+    cos_sun_radius: f64,
+    sun_radiance: Color,
+    intensity: f64,
+    perez_luminance: Perez,
+    perez_x: Perez,
+    perez_y: Perez,
+    // Zenith luminance and chromaticity, each divided by the Perez function
+    // at the zenith.
+    zenith_luminance: f64,
+    zenith_x: f64,
+    zenith_y: f64,
+}
+
+impl Sky {
+    pub fn create(elevation: f64, azimuth: f64, turbidity: f64, intensity: f64) -> Sky {
+        let elevation_radians = degrees_to_radians(elevation);
+        let azimuth_radians = degrees_to_radians(azimuth);
+        let sun_direction = Vec3 {
+            x: elevation_radians.cos() * azimuth_radians.sin(),
+            y: elevation_radians.sin(),
+            z: -elevation_radians.cos() * azimuth_radians.cos(),
+        };
+        let t = turbidity;
+        let theta_sun = PI / 2.0 - elevation_radians;
+
+        let perez_luminance = Perez {
+            a: 0.1787 * t - 1.4630,
+            b: -0.3554 * t + 0.4275,
+            c: -0.0227 * t + 5.3251,
+            d: 0.1206 * t - 2.5771,
+            e: -0.0670 * t + 0.3703,
+        };
+        let perez_x = Perez {
+            a: -0.0193 * t - 0.2592,
+            b: -0.0665 * t + 0.0008,
+            c: -0.0004 * t + 0.2125,
+            d: -0.0641 * t - 0.8989,
+            e: -0.0033 * t + 0.0452,
+        };
+        let perez_y = Perez {
+            a: -0.0167 * t - 0.2608,
+            b: -0.0950 * t + 0.0092,
+            c: -0.0079 * t + 0.2102,
+            d: -0.0441 * t - 1.6537,
+            e: -0.0109 * t + 0.0529,
+        };
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_sun);
+        let luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let chromaticity = |m: [[f64; 4]; 3]| -> f64 {
+            let powers = [theta_sun.powi(3), theta_sun.powi(2), theta_sun, 1.0];
+            let mut result = 0.0;
+            for (weight, row) in [t * t, t, 1.0].iter().zip(m.iter()) {
+                for (power, coefficient) in powers.iter().zip(row.iter()) {
+                    result += weight * coefficient * power;
+                }
+            }
+            return result;
+        };
+        let x = chromaticity([
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let y = chromaticity([
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+
+        return Sky {
+            sun_direction,
+            cos_sun_radius: SUN_ANGULAR_RADIUS.cos(),
+            sun_radiance: sun_radiance(theta_sun, turbidity),
+            intensity,
+            perez_luminance,
+            perez_x,
+            perez_y,
+            zenith_luminance: luminance.max(0.0) / perez_luminance.value(1.0, theta_sun),
+            zenith_x: x / perez_x.value(1.0, theta_sun),
+            zenith_y: y / perez_y.value(1.0, theta_sun),
+        };
+    }
+
+    pub fn value(&self, direction: Vec3) -> Color {
+        let d = unit_vector(direction);
+        let cos_gamma = dot(d, self.sun_direction);
+
+        // Below the horizon the sky keeps the colour it has at the horizon.
+        let cos_theta = d.y.max(0.01);
+        let gamma = cos_gamma.clamp(-1.0, 1.0).acos();
+        let luminance = self.zenith_luminance * self.perez_luminance.value(cos_theta, gamma);
+        let x = self.zenith_x * self.perez_x.value(cos_theta, gamma);
+        let y = self.zenith_y * self.perez_y.value(cos_theta, gamma);
+        let mut color = xyy_to_rgb(x, y, luminance);
+
+        if cos_gamma >= self.cos_sun_radius && d.y >= 0.0 {
+            color.add_assign(self.sun_radiance);
+        }
+        return color * (LUMINANCE_SCALE * self.intensity);
+    }
+
+    // A mixture of directions in the sun disk and directions uniformly
+    // over the sphere for the rest of the sky.
+    pub fn pdf_value(&self, direction: Vec3) -> f64 {
+        let mut sun_pdf = 0.0;
+        if dot(unit_vector(direction), self.sun_direction) >= self.cos_sun_radius {
+            sun_pdf = 1.0 / (2.0 * PI * (1.0 - self.cos_sun_radius));
+        }
+        return SUN_SAMPLING_PROBABILITY * sun_pdf + (1.0 - SUN_SAMPLING_PROBABILITY) / (4.0 * PI);
+    }
+
+    pub fn random(&self, rng: &mut RandomNumberGenerator) -> Vec3 {
+        if rng.random_double() >= SUN_SAMPLING_PROBABILITY {
+            return rng.random_unit_vector();
+        }
+        let r1 = rng.random_double();
+        let r2 = rng.random_double();
+        let z = 1.0 + r2 * (self.cos_sun_radius - 1.0);
+        let phi = 2.0 * PI * r1;
+        let sin_theta = (1.0 - z * z).sqrt();
+        return Onb::build_from_w(self.sun_direction).local(Vec3 {
+            x: phi.cos() * sin_theta,
+            y: phi.sin() * sin_theta,
+            z,
+        });
+    }
+}
+
+// Linear sRGB from CIE xyY.
+fn xyy_to_rgb(x: f64, y: f64, luminance: f64) -> Color {
+    if y <= 0.0 {
+        return Color {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        };
+    }
+    let big_x = x / y * luminance;
+    let big_z = (1.0 - x - y) / y * luminance;
+    return Color {
+        x: (3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z).max(0.0),
+        y: (-0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z).max(0.0),
+        z: (0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z).max(0.0),
+    };
+}
+
+// Sunlight after Rayleigh scattering by the air and Mie scattering by the
+// haze, evaluated at a red, a green and a blue wavelength.
+fn sun_radiance(theta_sun: f64, turbidity: f64) -> Color {
+    // Relative optical mass of the air along the way to the sun.
+    let zenith_degrees = theta_sun.to_degrees();
+    let air_mass = 1.0 / (theta_sun.cos() + 0.15 * (93.885 - zenith_degrees).powf(-1.253));
+    // Ångström's turbidity coefficient.
+    let beta = 0.04608 * turbidity - 0.04586;
+
+    let transmittance = |wavelength: f64| -> f64 {
+        let rayleigh = 0.008735 * wavelength.powf(-4.08);
+        let aerosol = beta * wavelength.powf(-1.3);
+        return (-(rayleigh + aerosol) * air_mass).exp();
+    };
+    return Color {
+        x: SUN_LUMINANCE * transmittance(0.65),
+        y: SUN_LUMINANCE * transmittance(0.55),
+        z: SUN_LUMINANCE * transmittance(0.45),
+    };
+}
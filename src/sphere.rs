@@ -1,18 +1,37 @@
+use super::aabb::*;
 use super::hit_record::*;
 use super::hittable::*;
 use super::material::*;
+use super::onb::*;
 use super::ray::*;
+use super::rng::*;
 use super::vec3::*;
-use std::rc::Rc;
+use std::f64::consts::PI;
+use std::sync::Arc;
 
 pub struct Sphere {
     pub center: Point3,
     pub radius: f64,
-    pub material: Rc<dyn Material>,
+    pub material: Arc<dyn Material>,
+}
+
+// Maps a point on the unit sphere to u = phi / 2pi around the Y axis, starting
+// from X=-1, and v = theta / pi from Y=-1 up to Y=+1.
+pub fn sphere_uv(p: Point3) -> (f64, f64) {
+    let theta = (-p.y).acos();
+    let phi = (-p.z).atan2(p.x) + PI;
+    return (phi / (2.0 * PI), theta / PI);
 }
 
 impl Hittable for Sphere {
-    fn hit(&self, r: Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+    fn hit(
+        &self,
+        _: &mut RandomNumberGenerator,
+        r: Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+    ) -> bool {
         let oc = r.origin - self.center;
         let a = r.direction.length_squared();
         let half_b = dot(oc, r.direction);
@@ -37,8 +56,66 @@ impl Hittable for Sphere {
         rec.p = r.at(rec.t);
         let outward_normal = (rec.p - self.center) / self.radius;
         rec.set_face_normal(r, outward_normal);
+        (rec.u, rec.v) = sphere_uv(outward_normal);
         rec.material = self.material.clone();
 
         return true;
     }
+
+    fn bounding_box(&self, output_box: &mut Aabb) -> bool {
+        let r = Vec3 {
+            x: self.radius,
+            y: self.radius,
+            z: self.radius,
+        };
+        *output_box = Aabb {
+            minimum: self.center - r,
+            maximum: self.center + r,
+        };
+        return true;
+    }
+
+    // Samples the cone of directions the sphere covers from outside, or all
+    // directions from inside.
+    fn pdf_value(&self, rng: &mut RandomNumberGenerator, origin: Point3, direction: Vec3) -> f64 {
+        let mut rec = HitRecord::new();
+        let r = Ray {
+            origin,
+            direction,
+            time: 0.0,
+        };
+        if !self.hit(rng, r, 0.001, f64::INFINITY, &mut rec) {
+            return 0.0;
+        }
+
+        let distance_squared = (self.center - origin).length_squared();
+        let radius_squared = self.radius * self.radius;
+        if distance_squared <= radius_squared {
+            return 1.0 / (4.0 * PI);
+        }
+        let cos_theta_max = (1.0 - radius_squared / distance_squared).sqrt();
+        let solid_angle = 2.0 * PI * (1.0 - cos_theta_max);
+        return 1.0 / solid_angle;
+    }
+
+    fn random(&self, rng: &mut RandomNumberGenerator, origin: Point3) -> Vec3 {
+        let direction = self.center - origin;
+        let distance_squared = direction.length_squared();
+        let radius_squared = self.radius * self.radius;
+        if distance_squared <= radius_squared {
+            return rng.random_unit_vector();
+        }
+
+        let r1 = rng.random_double();
+        let r2 = rng.random_double();
+        let z = 1.0 + r2 * ((1.0 - radius_squared / distance_squared).sqrt() - 1.0);
+        let phi = 2.0 * PI * r1;
+        let sin_theta = (1.0 - z * z).sqrt();
+        let uvw = Onb::build_from_w(direction);
+        return uvw.local(Vec3 {
+            x: phi.cos() * sin_theta,
+            y: phi.sin() * sin_theta,
+            z,
+        });
+    }
 }
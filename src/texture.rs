@@ -0,0 +1,122 @@
+use super::image_reader::*;
+use super::perlin::*;
+use super::vec3::*;
+use std::sync::Arc;
+
+// A colour that varies over a surface, looked up by the hit's surface
+// coordinates (u, v) or its position p.
+pub trait Texture: Send + Sync {
+    fn value(&self, u: f64, v: f64, p: Point3) -> Color;
+}
+
+pub struct SolidColor {
+    pub color: Color,
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f64, _v: f64, _p: Point3) -> Color {
+        return self.color;
+    }
+}
+
+// A 3D checkerboard of cubes with side `scale`, alternating between two
+// textures.
+pub struct Checker {
+    pub scale: f64,
+    pub even: Arc<dyn Texture>,
+    pub odd: Arc<dyn Texture>,
+}
+
+impl Texture for Checker {
+    fn value(&self, u: f64, v: f64, p: Point3) -> Color {
+        let x = (p.x / self.scale).floor() as i64;
+        let y = (p.y / self.scale).floor() as i64;
+        let z = (p.z / self.scale).floor() as i64;
+
+        if (x + y + z).rem_euclid(2) == 0 {
+            return self.even.value(u, v, p);
+        }
+        return self.odd.value(u, v, p);
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum NoisePattern {
+    Noise,
+    Turbulence,
+    Fbm,
+    Marble,
+    Wood,
+}
+
+impl NoisePattern {
+    pub fn from_name(name: &str) -> Option<NoisePattern> {
+        return match name {
+            "noise" => Some(NoisePattern::Noise),
+            "turbulence" => Some(NoisePattern::Turbulence),
+            "fbm" => Some(NoisePattern::Fbm),
+            "marble" => Some(NoisePattern::Marble),
+            "wood" => Some(NoisePattern::Wood),
+            _ => None,
+        };
+    }
+}
+
+// Procedural noise blending between the colours `low` and `high`. `scale` is
+// the frequency of the noise and `octaves` the number of layers summed by the
+// fractal patterns.
+pub struct NoiseTexture {
+    pub noise: Arc<Perlin>,
+    pub pattern: NoisePattern,
+    pub scale: f64,
+    pub octaves: i32,
+    pub low: Color,
+    pub high: Color,
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _u: f64, _v: f64, p: Point3) -> Color {
+        let q = p * self.scale;
+        let t = match self.pattern {
+            NoisePattern::Noise => 0.5 * (1.0 + self.noise.noise(q)),
+            NoisePattern::Turbulence => self.noise.turbulence(q, self.octaves),
+            NoisePattern::Fbm => 0.5 * (1.0 + self.noise.fbm(q, self.octaves)),
+            // Veins from a sine wave along z distorted by turbulence.
+            NoisePattern::Marble => {
+                0.5 * (1.0 + (q.z + 10.0 * self.noise.turbulence(q, self.octaves)).sin())
+            }
+            // Growth rings around the y axis, made irregular by the noise.
+            NoisePattern::Wood => {
+                let rings =
+                    (q.x * q.x + q.z * q.z).sqrt() + 0.5 * self.noise.fbm(q * 0.5, self.octaves);
+                rings - rings.floor()
+            }
+        };
+        let t = t.clamp(0.0, 1.0);
+        return self.low * (1.0 - t) + self.high * t;
+    }
+}
+
+// An image wrapped over the surface coordinates, u running left to right and
+// v bottom to top.
+pub struct ImageTexture {
+    image: ImageData,
+}
+
+impl ImageTexture {
+    pub fn load(path: &str) -> Result<ImageTexture, String> {
+        let image = read_image(path)?;
+        return Ok(ImageTexture { image });
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: Point3) -> Color {
+        let u = u.clamp(0.0, 1.0);
+        let v = 1.0 - v.clamp(0.0, 1.0);
+
+        let x = ((u * self.image.width as f64) as usize).min(self.image.width - 1);
+        let y = ((v * self.image.height as f64) as usize).min(self.image.height - 1);
+        return self.image.pixel(x, y);
+    }
+}
@@ -122,6 +122,18 @@ impl ops::Div<f64> for Vec3 {
     }
 }
 
+impl ops::Index<usize> for Vec3 {
+    type Output = f64;
+
+    fn index(&self, axis: usize) -> &f64 {
+        return match axis {
+            0 => &self.x,
+            1 => &self.y,
+            _ => &self.z,
+        };
+    }
+}
+
 pub fn unit_vector(v: Vec3) -> Vec3 {
     return v / v.length();
 }
@@ -153,3 +165,373 @@ pub fn refract(uv: Vec3, n: Vec3, etai_over_etat: f64) -> Vec3 {
     let r_out_parallel = n * -(1.0 - r_out_perp.length_squared()).abs().sqrt();
     return r_out_perp + r_out_parallel;
 }
+
+pub fn min_vec3(a: Vec3, b: Vec3) -> Vec3 {
+    return Vec3 {
+        x: a.x.min(b.x),
+        y: a.y.min(b.y),
+        z: a.z.min(b.z),
+    };
+}
+
+pub fn max_vec3(a: Vec3, b: Vec3) -> Vec3 {
+    return Vec3 {
+        x: a.x.max(b.x),
+        y: a.y.max(b.y),
+        z: a.z.max(b.z),
+    };
+}
+
+// A 4x4 matrix for affine transforms of points and vectors, stored by rows
+// and applied to column vectors.
+#[derive(Copy, Clone)]
+pub struct Mat4 {
+    pub m: [[f64; 4]; 4],
+}
+
+impl Mat4 {
+    pub fn identity() -> Mat4 {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            row[i] = 1.0;
+        }
+        return Mat4 { m };
+    }
+
+    pub fn translation(offset: Vec3) -> Mat4 {
+        let mut result = Mat4::identity();
+        result.m[0][3] = offset.x;
+        result.m[1][3] = offset.y;
+        result.m[2][3] = offset.z;
+        return result;
+    }
+
+    pub fn scaling(factors: Vec3) -> Mat4 {
+        let mut result = Mat4::identity();
+        result.m[0][0] = factors.x;
+        result.m[1][1] = factors.y;
+        result.m[2][2] = factors.z;
+        return result;
+    }
+
+    // Counterclockwise rotation by `degrees` about `axis`, looking down the
+    // axis towards the origin.
+    pub fn rotation(axis: Vec3, degrees: f64) -> Mat4 {
+        let a = unit_vector(axis);
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let t = 1.0 - cos;
+        let mut result = Mat4::identity();
+        result.m[0] = [
+            t * a.x * a.x + cos,
+            t * a.x * a.y - sin * a.z,
+            t * a.x * a.z + sin * a.y,
+            0.0,
+        ];
+        result.m[1] = [
+            t * a.x * a.y + sin * a.z,
+            t * a.y * a.y + cos,
+            t * a.y * a.z - sin * a.x,
+            0.0,
+        ];
+        result.m[2] = [
+            t * a.x * a.z - sin * a.y,
+            t * a.y * a.z + sin * a.x,
+            t * a.z * a.z + cos,
+            0.0,
+        ];
+        return result;
+    }
+
+    pub fn transpose(&self) -> Mat4 {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = self.m[j][i];
+            }
+        }
+        return Mat4 { m };
+    }
+
+    // Gauss-Jordan elimination with partial pivoting, None when the matrix
+    // is singular.
+    pub fn inverse(&self) -> Option<Mat4> {
+        let mut a = self.m;
+        let mut result = Mat4::identity().m;
+        for column in 0..4 {
+            let pivot = (column..4)
+                .max_by(|&i, &j| a[i][column].abs().total_cmp(&a[j][column].abs()))
+                .unwrap();
+            if a[pivot][column].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(column, pivot);
+            result.swap(column, pivot);
+
+            let scale = 1.0 / a[column][column];
+            for j in 0..4 {
+                a[column][j] *= scale;
+                result[column][j] *= scale;
+            }
+            for i in 0..4 {
+                if i == column {
+                    continue;
+                }
+                let factor = a[i][column];
+                for j in 0..4 {
+                    a[i][j] -= factor * a[column][j];
+                    result[i][j] -= factor * result[column][j];
+                }
+            }
+        }
+        return Some(Mat4 { m: result });
+    }
+
+    pub fn transform_point(&self, p: Point3) -> Point3 {
+        let m = &self.m;
+        return Point3 {
+            x: m[0][0] * p.x + m[0][1] * p.y + m[0][2] * p.z + m[0][3],
+            y: m[1][0] * p.x + m[1][1] * p.y + m[1][2] * p.z + m[1][3],
+            z: m[2][0] * p.x + m[2][1] * p.y + m[2][2] * p.z + m[2][3],
+        };
+    }
+
+    // Transforms a direction, which the translation does not affect.
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        let m = &self.m;
+        return Vec3 {
+            x: m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+            y: m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+            z: m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z,
+        };
+    }
+}
+
+impl ops::Mul<Mat4> for Mat4 {
+    type Output = Mat4;
+
+    // The product applies `b` first and then `self`.
+    fn mul(self, b: Mat4) -> Mat4 {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[i][k] * b.m[k][j]).sum();
+            }
+        }
+        return Mat4 { m };
+    }
+}
+
+// A unit quaternion w + xi + yj + zk for rotations that can be interpolated.
+#[derive(Copy, Clone)]
+pub struct Quaternion {
+    pub w: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Quaternion {
+    pub fn identity() -> Quaternion {
+        return Quaternion {
+            w: 1.0,
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        };
+    }
+
+    // The same rotation as `Mat4::rotation`.
+    pub fn rotation(axis: Vec3, degrees: f64) -> Quaternion {
+        let a = unit_vector(axis);
+        let (sin, cos) = (degrees.to_radians() / 2.0).sin_cos();
+        return Quaternion {
+            w: cos,
+            x: a.x * sin,
+            y: a.y * sin,
+            z: a.z * sin,
+        };
+    }
+
+    fn dot(&self, b: Quaternion) -> f64 {
+        return self.w * b.w + self.x * b.x + self.y * b.y + self.z * b.z;
+    }
+
+    fn scaled(&self, factor: f64) -> Quaternion {
+        return Quaternion {
+            w: self.w * factor,
+            x: self.x * factor,
+            y: self.y * factor,
+            z: self.z * factor,
+        };
+    }
+
+    fn plus(&self, b: Quaternion) -> Quaternion {
+        return Quaternion {
+            w: self.w + b.w,
+            x: self.x + b.x,
+            y: self.y + b.y,
+            z: self.z + b.z,
+        };
+    }
+
+    // Spherical linear interpolation from `self` at s = 0 to `b` at s = 1,
+    // turning at a constant rate the short way round.
+    pub fn slerp(&self, b: Quaternion, s: f64) -> Quaternion {
+        let mut b = b;
+        let mut cos = self.dot(b);
+        if cos < 0.0 {
+            b = b.scaled(-1.0);
+            cos = -cos;
+        }
+        let result = if cos > 0.9995 {
+            // Nearly the same rotation, where the sines below vanish.
+            self.scaled(1.0 - s).plus(b.scaled(s))
+        } else {
+            let theta = cos.acos();
+            self.scaled(((1.0 - s) * theta).sin())
+                .plus(b.scaled((s * theta).sin()))
+        };
+        return result.scaled(1.0 / result.dot(result).sqrt());
+    }
+
+    pub fn to_mat4(self) -> Mat4 {
+        let (w, x, y, z) = (self.w, self.x, self.y, self.z);
+        let mut result = Mat4::identity();
+        result.m[0] = [
+            1.0 - 2.0 * (y * y + z * z),
+            2.0 * (x * y - w * z),
+            2.0 * (x * z + w * y),
+            0.0,
+        ];
+        result.m[1] = [
+            2.0 * (x * y + w * z),
+            1.0 - 2.0 * (x * x + z * z),
+            2.0 * (y * z - w * x),
+            0.0,
+        ];
+        result.m[2] = [
+            2.0 * (x * z - w * y),
+            2.0 * (y * z + w * x),
+            1.0 - 2.0 * (x * x + y * y),
+            0.0,
+        ];
+        return result;
+    }
+}
+
+impl ops::Mul<Quaternion> for Quaternion {
+    type Output = Quaternion;
+
+    // Like for matrices, the product applies `b` first and then `self`.
+    fn mul(self, b: Quaternion) -> Quaternion {
+        return Quaternion {
+            w: self.w * b.w - self.x * b.x - self.y * b.y - self.z * b.z,
+            x: self.w * b.x + self.x * b.w + self.y * b.z - self.z * b.y,
+            y: self.w * b.y - self.x * b.z + self.y * b.w + self.z * b.x,
+            z: self.w * b.z + self.x * b.y - self.y * b.x + self.z * b.w,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vec3(x: f64, y: f64, z: f64) -> Vec3 {
+        return Vec3 { x, y, z };
+    }
+
+    fn assert_near(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-12, "{} != {}", a, b);
+    }
+
+    // A translation, a rotation about a slanted axis and a non-uniform
+    // scale, as a transform list builds them.
+    fn composed() -> Mat4 {
+        return Mat4::translation(vec3(1.0, -2.0, 3.0))
+            * Mat4::rotation(vec3(1.0, 1.0, 0.0), 30.0)
+            * Mat4::scaling(vec3(2.0, 3.0, 0.5));
+    }
+
+    #[test]
+    fn a_transform_times_its_inverse_is_the_identity() {
+        let m = composed();
+        let product = m * m.inverse().unwrap();
+        let identity = Mat4::identity();
+        for (row, identity_row) in product.m.iter().zip(identity.m.iter()) {
+            for (value, expected) in row.iter().zip(identity_row.iter()) {
+                assert!(
+                    (value - expected).abs() < 1e-12,
+                    "{} != {}",
+                    value,
+                    expected
+                );
+            }
+        }
+        assert!(Mat4::scaling(vec3(1.0, 0.0, 1.0)).inverse().is_none());
+    }
+
+    #[test]
+    fn rotations_turn_counterclockwise_about_their_axis() {
+        let x = vec3(1.0, 0.0, 0.0);
+        let z_rotation = Mat4::rotation(vec3(0.0, 0.0, 1.0), 90.0);
+        assert_near(z_rotation.transform_vector(x), vec3(0.0, 1.0, 0.0));
+        let y_rotation = Mat4::rotation(vec3(0.0, 1.0, 0.0), 90.0);
+        assert_near(y_rotation.transform_vector(x), vec3(0.0, 0.0, -1.0));
+        // Vectors ignore the translation, points do not.
+        let moved = Mat4::translation(vec3(0.0, 0.0, 5.0)) * z_rotation;
+        assert_near(moved.transform_vector(x), vec3(0.0, 1.0, 0.0));
+        assert_near(moved.transform_point(x), vec3(0.0, 1.0, 5.0));
+    }
+
+    #[test]
+    fn normals_stay_perpendicular_under_non_uniform_scale() {
+        let m = composed();
+        let normal_matrix = m.inverse().unwrap().transpose();
+        let normal = unit_vector(vec3(1.0, 2.0, 2.0));
+        for tangent in [
+            vec3(2.0, -1.0, 0.0),
+            vec3(0.0, 1.0, -1.0),
+            vec3(2.0, 1.0, -2.0),
+        ] {
+            assert!(dot(tangent, normal).abs() < 1e-12);
+            let transformed = dot(
+                m.transform_vector(tangent),
+                normal_matrix.transform_vector(normal),
+            );
+            assert!(transformed.abs() < 1e-12, "{}", transformed);
+        }
+        // Transforming the normal like a tangent would not keep it so.
+        let wrong = dot(
+            m.transform_vector(vec3(2.0, -1.0, 0.0)),
+            m.transform_vector(normal),
+        );
+        assert!(wrong.abs() > 0.1);
+    }
+
+    fn assert_same_rotation(a: Quaternion, b: Quaternion) {
+        // q and -q are the same rotation.
+        let cos = a.w * b.w + a.x * b.x + a.y * b.y + a.z * b.z;
+        assert!((cos.abs() - 1.0).abs() < 1e-12, "{}", cos);
+    }
+
+    #[test]
+    fn slerp_runs_between_its_ends_on_unit_quaternions() {
+        let start = Quaternion::rotation(vec3(1.0, 0.0, 0.0), 20.0);
+        let end = Quaternion::rotation(vec3(0.0, 1.0, 1.0), 150.0);
+        assert_same_rotation(start.slerp(end, 0.0), start);
+        assert_same_rotation(start.slerp(end, 1.0), end);
+        for i in 1..10 {
+            let q = start.slerp(end, i as f64 / 10.0);
+            let length = (q.w * q.w + q.x * q.x + q.y * q.y + q.z * q.z).sqrt();
+            assert!((length - 1.0).abs() < 1e-12, "{}", length);
+        }
+
+        // Halfway through a turn about one axis is half the angle.
+        let turn =
+            Quaternion::identity().slerp(Quaternion::rotation(vec3(0.0, 0.0, 1.0), 90.0), 0.5);
+        let x = vec3(1.0, 0.0, 0.0);
+        let expected = Mat4::rotation(vec3(0.0, 0.0, 1.0), 45.0).transform_vector(x);
+        assert_near(turn.to_mat4().transform_vector(x), expected);
+    }
+}